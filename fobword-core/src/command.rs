@@ -0,0 +1,355 @@
+use crate::config::SymbolLevel;
+use crate::error::DataHandleError;
+use crate::pace::HostProfile;
+
+/// Lines starting with this character are commands, everything else is the name of a macro.
+///
+/// Typing it twice escapes it, `::name` is the macro `:name`.
+pub const PREFIX: char = ':';
+
+/// The name and usage of every command, in the order `help` shows them.
+pub const COMMANDS: [(&str, &str); 16] =
+[
+    ("type", "type [name] [--profile]"),
+    ("new", "new [name]"),
+    ("generate", "gen [name] [length] [--flag] [comment]"),
+    ("regenerate", "regen [name] [length] [--flag] [comment]"),
+    ("update", "update [name]"),
+    ("delete", "delete [name]"),
+    ("hint", "hint [name]"),
+    ("comment", "comment [name]"),
+    ("host", "host [name] [--profile]"),
+    ("save", "save"),
+    ("change", "change"),
+    ("duress", "duress"),
+    ("lock", "lock"),
+    ("exit", "exit"),
+    ("reset", "reset"),
+    ("help", "help"),
+];
+
+/// The symbol level flags of `generate` and `regenerate`.
+const SYMBOL_FLAGS: [(&str, SymbolLevel); 5] =
+[
+    ("--lower", SymbolLevel::LowercaseAscii),
+    ("--upper", SymbolLevel::UppercaseAscii),
+    ("--numbers", SymbolLevel::Numbers),
+    ("--no-symbols", SymbolLevel::Numbers),
+    ("--symbols", SymbolLevel::Symbols),
+];
+
+/// A line typed at the unlocked prompt.
+///
+/// Arguments that are left out are `None`, the caller asks for them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command
+{
+    /// Type the password of the macro with this name
+    Type(String),
+    /// Type a password at the pace of a host profile, the profile of the macro if not given
+    TypeAt { name: Option<String>, host: Option<HostProfile> },
+    New { name: Option<String> },
+    Generate(Generate),
+    Regenerate(Generate),
+    Update { name: Option<String> },
+    Delete { name: Option<String> },
+    Hint { name: Option<String> },
+    Comment { name: Option<String> },
+    /// Set the host profile a macro is typed at
+    Host { name: Option<String>, host: Option<HostProfile> },
+    Save,
+    Change,
+    Duress,
+    Lock,
+    Exit,
+    Reset,
+    Help,
+}
+
+/// The arguments of `generate` and `regenerate`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Generate
+{
+    pub name: Option<String>,
+    pub length: Option<u8>,
+    pub symbols: Option<SymbolLevel>,
+    /// The words after the length, joined by spaces
+    pub comment: Option<String>,
+}
+
+impl Command
+{
+    /// Parse a line typed at the unlocked prompt.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::command::{Command, Generate};
+    /// # use fobword_core::config::SymbolLevel;
+    /// # use fobword_core::error::DataHandleError;
+    /// assert_eq!(Command::Type(String::from("save")), Command::parse("save")?);
+    /// assert_eq!(Command::Save, Command::parse(":save")?);
+    ///
+    /// let generate = Generate { name: Some(String::from("github")), length: Some(24), symbols: Some(SymbolLevel::Numbers), comment: None };
+    /// assert_eq!(Command::Generate(generate), Command::parse(":gen github 24 --no-symbols")?);
+    /// # Ok::<(), DataHandleError>(())
+    /// ```
+    ///
+    /// # Errors
+    /// `InvalidCommand` with a message for the user when the command or its arguments are unknown
+    pub fn parse(line: &str) -> Result<Command, DataHandleError>
+    {
+        let line = match line.strip_prefix(PREFIX)
+        {
+            Some(line) if line.starts_with(PREFIX) => return Ok(Command::Type(line.to_owned())),
+            Some(line) => line,
+            None => return Ok(Command::Type(line.to_owned())),
+        };
+
+        let mut words = line.split_whitespace();
+        let command = match words.next()
+        {
+            Some(command) => command.to_lowercase(),
+            None => return invalid(format!("Type a command after '{}', {}help lists them", PREFIX, PREFIX)),
+        };
+        let arguments: Vec<&str> = words.collect();
+
+        match command.as_str()
+        {
+            "new" => Ok(Command::New { name: Command::name(&arguments)? }),
+            "gen" | "generate" => Ok(Command::Generate(Command::generate(&arguments)?)),
+            "regen" | "regenerate" => Ok(Command::Regenerate(Command::generate(&arguments)?)),
+            "update" => Ok(Command::Update { name: Command::name(&arguments)? }),
+            "delete" => Ok(Command::Delete { name: Command::name(&arguments)? }),
+            "hint" => Ok(Command::Hint { name: Command::name(&arguments)? }),
+            "comment" => Ok(Command::Comment { name: Command::name(&arguments)? }),
+            "type" =>
+            {
+                let (name, host) = Command::name_and_host(&arguments)?;
+                Ok(Command::TypeAt { name, host })
+            },
+            "host" =>
+            {
+                let (name, host) = Command::name_and_host(&arguments)?;
+                Ok(Command::Host { name, host })
+            },
+            _ if !arguments.is_empty() && Command::without_arguments(&command).is_some() =>
+            {
+                invalid(format!("{} takes no arguments", command))
+            },
+            _ => match Command::without_arguments(&command)
+            {
+                Some(command) => Ok(command),
+                None => invalid(format!("Unknown command '{}', {}help lists them", command, PREFIX)),
+            },
+        }
+    }
+
+    /// Complete a command name that is being typed, returns the text to add to the line.
+    ///
+    /// A single match is completed with a space after it, several matches are completed as far as they agree.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::command::Command;
+    /// assert_eq!(Some(String::from("ave ")), Command::complete(":s"));
+    /// assert_eq!(Some(String::from("e")), Command::complete(":r"));
+    /// assert_eq!(None, Command::complete("github"));
+    /// ```
+    pub fn complete(line: &str) -> Option<String>
+    {
+        let typed = line.strip_prefix(PREFIX)?;
+        if typed.contains(char::is_whitespace)
+        {
+            return None
+        }
+
+        let typed = typed.to_lowercase();
+        let matches: Vec<&str> = COMMANDS.iter()
+            .map(|(name, _)| *name)
+            .filter(|name| name.starts_with(&typed))
+            .collect();
+        let completion = match matches.as_slice()
+        {
+            [] => return None,
+            [single] => format!("{} ", &single[typed.len()..]),
+            [first, rest @ ..] =>
+            {
+                let common = rest.iter().fold(first.len(), |common, name|
+                {
+                    first.bytes().zip(name.bytes()).take(common).take_while(|(a, b)| a == b).count()
+                });
+                first[typed.len()..common].to_owned()
+            },
+        };
+        if completion.is_empty() { None } else { Some(completion) }
+    }
+
+    fn without_arguments(command: &str) -> Option<Command>
+    {
+        match command
+        {
+            "save" => Some(Command::Save),
+            "change" => Some(Command::Change),
+            "duress" => Some(Command::Duress),
+            "lock" => Some(Command::Lock),
+            "exit" => Some(Command::Exit),
+            "reset" => Some(Command::Reset),
+            "help" => Some(Command::Help),
+            _ => None,
+        }
+    }
+
+    fn name(arguments: &[&str]) -> Result<Option<String>, DataHandleError>
+    {
+        match arguments
+        {
+            [] => Ok(None),
+            [name] => Ok(Some(name.to_string())),
+            _ => invalid("Only one name, without spaces".to_owned()),
+        }
+    }
+
+    /// A name and a host profile flag, in any order.
+    fn name_and_host(arguments: &[&str]) -> Result<(Option<String>, Option<HostProfile>), DataHandleError>
+    {
+        let mut host = None;
+        let mut names = Vec::new();
+        for argument in arguments
+        {
+            match argument.strip_prefix("--")
+            {
+                Some(flag) => match HostProfile::ALL.iter().find(|profile| profile.name() == flag)
+                {
+                    Some(profile) => host = Some(*profile),
+                    None => return invalid(format!("Unknown option '{}'", argument)),
+                },
+                None => names.push(*argument),
+            }
+        }
+        Ok((Command::name(&names)?, host))
+    }
+
+    fn generate(arguments: &[&str]) -> Result<Generate, DataHandleError>
+    {
+        let mut generate = Generate::default();
+        let mut comment = Vec::new();
+        for argument in arguments
+        {
+            if argument.starts_with("--")
+            {
+                match SYMBOL_FLAGS.iter().find(|(flag, _)| flag == argument)
+                {
+                    Some((_, symbols)) => generate.symbols = Some(*symbols),
+                    None => return invalid(format!("Unknown option '{}'", argument)),
+                }
+            }
+            else if generate.name.is_none()
+            {
+                generate.name = Some(argument.to_string());
+            }
+            else if generate.length.is_none() && comment.is_empty()
+            {
+                match argument.parse::<u8>()
+                {
+                    Ok(length) => generate.length = Some(length),
+                    Err(_) => return invalid(format!("Length '{}' is not a number up to 255", argument)),
+                }
+            }
+            else
+            {
+                comment.push(*argument);
+            }
+        }
+        if !comment.is_empty()
+        {
+            generate.comment = Some(comment.join(" "));
+        }
+        Ok(generate)
+    }
+}
+
+fn invalid<T>(message: String) -> Result<T, DataHandleError>
+{
+    Err(DataHandleError::InvalidCommand(message))
+}
+
+#[cfg(test)]
+mod commandtests
+{
+    use super::*;
+
+    fn generate(name: Option<&str>, length: Option<u8>, symbols: Option<SymbolLevel>, comment: Option<&str>) -> Generate
+    {
+        Generate { name: name.map(String::from), length, symbols, comment: comment.map(String::from) }
+    }
+
+    #[test]
+    fn test_names_are_not_commands()
+    {
+        assert_eq!(Command::Type(String::from("save")), Command::parse("save").unwrap());
+        assert_eq!(Command::Type(String::from("my bank")), Command::parse("my bank").unwrap());
+        assert_eq!(Command::Type(String::from(":save")), Command::parse("::save").unwrap());
+    }
+
+    #[test]
+    fn test_parse_commands()
+    {
+        assert_eq!(Command::Save, Command::parse(":save").unwrap());
+        assert_eq!(Command::Lock, Command::parse(":LOCK").unwrap());
+        assert_eq!(Command::Help, Command::parse(": help ").unwrap());
+        assert_eq!(Command::New { name: None }, Command::parse(":new").unwrap());
+        assert_eq!(Command::Delete { name: Some(String::from("github")) }, Command::parse(":delete github").unwrap());
+    }
+
+    #[test]
+    fn test_parse_generate()
+    {
+        let expected = generate(Some("github"), Some(24), Some(SymbolLevel::Numbers), None);
+        assert_eq!(Command::Generate(expected), Command::parse(":gen github 24 --no-symbols").unwrap());
+
+        let expected = generate(Some("mail"), Some(16), Some(SymbolLevel::Symbols), Some("work account"));
+        assert_eq!(Command::Regenerate(expected), Command::parse(":regen --symbols mail 16 work account").unwrap());
+
+        assert_eq!(Command::Generate(Generate::default()), Command::parse(":generate").unwrap());
+    }
+
+    #[test]
+    fn test_parse_errors()
+    {
+        assert!(Command::parse(":").is_err());
+        assert!(Command::parse(":frobnicate").is_err());
+        assert!(Command::parse(":save now").is_err());
+        assert!(Command::parse(":new two names").is_err());
+        assert!(Command::parse(":gen github long").is_err());
+        assert!(Command::parse(":gen github 300").is_err());
+        assert!(Command::parse(":gen github 24 --emoji").is_err());
+        assert!(Command::parse(":type github --fast").is_err());
+        assert!(Command::parse(":host github mail").is_err());
+    }
+
+    #[test]
+    fn test_parse_host()
+    {
+        let expected = Command::TypeAt { name: Some(String::from("github")), host: Some(HostProfile::Bios) };
+        assert_eq!(expected, Command::parse(":type --bios github").unwrap());
+        assert_eq!(Command::TypeAt { name: None, host: None }, Command::parse(":type").unwrap());
+
+        let expected = Command::Host { name: Some(String::from("server")), host: Some(HostProfile::SlowVm) };
+        assert_eq!(expected, Command::parse(":host server --slow-vm").unwrap());
+        assert_eq!(Command::Host { name: Some(String::from("server")), host: None }, Command::parse(":host server").unwrap());
+    }
+
+    #[test]
+    fn test_complete()
+    {
+        assert_eq!(Some(String::from("ave ")), Command::complete(":s"));
+        assert_eq!(Some(String::from("e")), Command::complete(":r"));
+        assert_eq!(Some(String::from("enerate ")), Command::complete(":g"));
+        assert_eq!(Some(String::from("erate ")), Command::complete(":gen"));
+        assert_eq!(None, Command::complete(":generate "));
+        assert_eq!(Some(String::from(" ")), Command::complete(":help"));
+        assert_eq!(None, Command::complete(":x"));
+        assert_eq!(None, Command::complete(":gen git"));
+        assert_eq!(None, Command::complete("sa"));
+    }
+}
@@ -9,10 +9,14 @@ use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use rand::Rng;
 
 use crate::error::DataHandleError;
+use crate::pace::HostProfile;
 
 /// A configuration struct with optional settings and/or password-encryptable data.
 /// 
 /// T needs to implement serde serialize and deserialize
+///
+/// This is the format of configuration files that hold the settings and the data together,
+/// [`LockedData`] can also be serialized on its own.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config<T>
 {
@@ -48,18 +52,53 @@ impl<T> Config<T>
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 /// A structure used for protecting data through encryption.
-/// 
-/// The purpose of this struct is to lock up a `data` struct using a password, after which the blobs will be encrypted using AES256GCM.
-/// 
+///
+/// The purpose of this struct is to lock up a `data` struct using a password, after which it will be encrypted using AES256GCM.
+///
 /// Argon2 will be used for password verification, AES256GCM will be used to encrypt the data and the key.
+///
+/// Every `LockedData` holds two key slots that are serialized identically. A slot is opened by the password it was created with,
+/// so a second (duress) password can open a decoy `Data` without the file showing whether a decoy exists.
+/// A slot without a decoy is filled with a random password nobody knows.
+/// The whole `Data` of a slot, names, hints and comments included, is encrypted into a blob padded to a multiple of [`VAULT_PADDING`] bytes,
+/// so a decoy with a few entries looks the same as an empty filler.
 pub struct LockedData
+{
+    // The first slot keeps the field names of the single slot format, so older configuration files still load
+    #[serde(flatten)]
+    primary: KeySlot,
+    #[serde(default)]
+    alternate: Option<KeySlot>,
+    #[serde(skip)]
+    wiped: bool,
+}
+
+/// Marks the slot as the decoy slot, stored inside the encrypted key.
+const DECOY_SLOT: u8 = 1;
+/// Opening the decoy slot wipes the other slot.
+const WIPE_ON_UNLOCK: u8 = 2;
+/// The encrypted `Data` of a slot is padded to a multiple of this many bytes.
+pub const VAULT_PADDING: usize = 16 * 1024;
+/// The bytes in front of the serialized `Data` that hold its length.
+const LENGTH_PREFIX: usize = 4;
+/// The message of the `LockedData` error for a slot that decrypts but does not hold a `Data`.
+pub const DAMAGED_VAULT: &str = "the vault is damaged";
+
+/// A single password protected slot of a `LockedData`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct KeySlot
 {
     verification_hash: String,
     salt: String,
     #[serde(deserialize_with = "des_string_as_key")]
     #[serde(serialize_with = "ser_key_as_string")]
     key: Vec<u8>,
-    data: Data,
+    /// The padded and encrypted `Data`, b64 encoded
+    #[serde(default)]
+    vault: String,
+    /// Files written before the whole `Data` was encrypted only have the blobs encrypted, they are read from here
+    #[serde(default, skip_serializing)]
+    data: Option<Data>,
 }
 
 /// yaml arrays are written multiline in serde, the conversion to string makes it a single line
@@ -71,27 +110,17 @@ where
     s.serialize_str(&key_as_string)
 }
 
-/// Deserialize the key string back into a Vec<u8> 
+/// Deserialize the key string back into a Vec<u8>
 pub fn des_string_as_key<'de, D>(d: D) -> Result<Vec<u8>, D::Error> where D: Deserializer<'de>
 {
     let key_as_string = String::deserialize(d)?;
     Ok(key_as_string.as_bytes().to_owned())
 }
 
-impl LockedData
+impl KeySlot
 {
-    /// Creates a new LockData which holds variables used for encrypting and decrypting data.
-    /// 
-    /// # Examples
-    /// ```
-    /// let mut data_lock = LockedData::new("password")?;
-    /// let mut data = data_lock.unlock("password")?;
-    /// 
-    /// data_lock.lock("password", data)?;
-    /// ```
-    /// # Errors
-    /// [Argon / password_hash](https://docs.rs/password-hash/0.3.2/password_hash/errors/enum.Error.html)
-    pub fn new(password: &str) -> Result<LockedData, DataHandleError>
+    /// Create a new slot with an empty `Data`, opened by `password`.
+    fn new(password: &str, flags: u8) -> Result<KeySlot, DataHandleError>
     {
         // Generate a new key for AES256GCM
         let mut key = vec![0u8;32];
@@ -104,107 +133,241 @@ impl LockedData
         let verification_salt = SaltString::generate(rand::rngs::OsRng).as_str().to_owned();
         let verification_hash = ArgonHelper::argon2_phc(&hashed_password.to_string(), &verification_salt)?.to_string();
 
-        let data = Data::new();
+        let vault = KeySlot::seal_data(&key, &Data::new())?;
 
         let decoded_hash = base64::decode(hashed_password)?;
-        let key = AesHelper::encrypt_with_key_to_b64(&decoded_hash, key.as_ref())?;
+        let key = KeySlot::seal_key(&decoded_hash, &key, flags)?;
+        Ok(KeySlot {verification_hash, salt, key, vault, data: None})
+    }
+
+    /// Create a slot that can not be opened, used when there is no decoy.
+    fn filler() -> Result<KeySlot, DataHandleError>
+    {
+        let mut password = vec![0u8;32];
+        rand::rngs::OsRng.fill(password.as_mut_slice());
+        KeySlot::new(&base64::encode(password), 0)
+    }
+
+    /// Encrypt the key together with the slot flags using the decoded password hash.
+    fn seal_key(decoded_hash: &[u8], key: &[u8], flags: u8) -> Result<Vec<u8>, DataHandleError>
+    {
+        let mut plaintext = key.to_owned();
+        plaintext.push(flags);
+        Ok(AesHelper::encrypt_with_key_to_b64(decoded_hash, plaintext.as_slice())?.as_bytes().to_owned())
+    }
 
-        let key = key.as_bytes().to_owned();
-        Ok(LockedData {verification_hash, salt, key, data})
+    /// Decrypt the key and the slot flags using the decoded password hash.
+    ///
+    /// Keys written before slots had flags are 32 bytes long and have no flags set.
+    fn open_key(&self, decoded_hash: &[u8]) -> Result<(Vec<u8>, u8), DataHandleError>
+    {
+        let mut key = AesHelper::decrypt_with_key_from_b64(decoded_hash, &self.key)?;
+        let flags = if key.len() > 32 { key.pop().unwrap_or(0) } else { 0 };
+        Ok((key, flags))
+    }
+
+    /// Serialize and encrypt a `Data` with the key, padded so its size only shows in steps of [`VAULT_PADDING`].
+    fn seal_data(key: &[u8], data: &Data) -> Result<String, DataHandleError>
+    {
+        let serialized = serde_yaml::to_string(data)?;
+        let length = LENGTH_PREFIX + serialized.len();
+        let padded_length = length.div_ceil(VAULT_PADDING) * VAULT_PADDING;
+
+        let mut plaintext = Vec::with_capacity(padded_length);
+        plaintext.extend_from_slice(&(serialized.len() as u32).to_be_bytes());
+        plaintext.extend_from_slice(serialized.as_bytes());
+        plaintext.resize(padded_length, 0);
+        AesHelper::encrypt_with_key_to_b64(key, plaintext.as_slice())
+    }
+
+    /// Decrypt the `Data` of the slot with the key, the blobs hold the passwords in plaintext afterwards.
+    fn open_data(&self, key: &[u8]) -> Result<Data, DataHandleError>
+    {
+        if let Some(data) = &self.data
+        {
+            let mut data = data.clone();
+            for (_index, data) in data.map.iter_mut()
+            {
+                let decrypted = AesHelper::decrypt_with_key_from_b64(key, data.blob.as_bytes())?;
+                data.blob = String::from_utf8(decrypted)?;
+            }
+            return Ok(data)
+        }
+
+        let plaintext = AesHelper::decrypt_with_key(key, base64::decode(&self.vault)?.as_slice())?;
+        let damaged = || DataHandleError::LockedData(DAMAGED_VAULT.to_string());
+        let prefix = plaintext.get(..LENGTH_PREFIX).ok_or_else(damaged)?;
+        let length = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        let serialized = plaintext.get(LENGTH_PREFIX..LENGTH_PREFIX + length).ok_or_else(damaged)?;
+        Ok(serde_yaml::from_str(str::from_utf8(serialized).map_err(|_| damaged())?)?)
+    }
+
+    /// Check that the encrypted fields are valid b64, so a damaged file is found before anyone unlocks it.
+    fn check(&self) -> Result<(), DataHandleError>
+    {
+        base64::decode(&self.key)?;
+        base64::decode(&self.vault)?;
+        Ok(())
+    }
+
+    /// Verify the password and return the argon2 hash on success.
+    fn verify(&self, password: &str) -> Result<String, DataHandleError>
+    {
+        ArgonHelper::verify_password(password, &self.salt, &self.verification_hash)
+    }
+}
+
+impl LockedData
+{
+    /// Serialize the locked data as a string of YAML.
+    pub fn to_yaml(&self) -> Result<String, DataHandleError>
+    {
+        Ok(serde_yaml::to_string(&self)?)
+    }
+
+    /// Deserialize the locked data from a string of YAML.
+    ///
+    /// # Errors
+    /// `YamlParseError` when the YAML is invalid, `B64ParseError` when a slot holds a damaged key or vault
+    pub fn from_yaml(buffer: &str) -> Result<Self, DataHandleError>
+    {
+        let data: LockedData = serde_yaml::from_str(buffer)?;
+        data.primary.check()?;
+        if let Some(alternate) = &data.alternate
+        {
+            alternate.check()?;
+        }
+        Ok(data)
+    }
+
+    /// Creates a new LockData which holds variables used for encrypting and decrypting data.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::config::LockedData;
+    /// # use fobword_core::error::DataHandleError;
+    /// let mut data_lock = LockedData::new("password")?;
+    /// let mut data = data_lock.unlock("password")?;
+    ///
+    /// data_lock.lock("password", data)?;
+    /// # Ok::<(), DataHandleError>(())
+    /// ```
+    /// # Errors
+    /// [Argon / password_hash](https://docs.rs/password-hash/0.3.2/password_hash/errors/enum.Error.html)
+    pub fn new(password: &str) -> Result<LockedData, DataHandleError>
+    {
+        let primary = KeySlot::new(password, 0)?;
+        let alternate = Some(KeySlot::filler()?);
+        Ok(LockedData { primary, alternate, wiped: false })
     }
 
 
     /// Decrypt a `Data` structure using a password and return it on success.
-    /// 
+    ///
     /// The password will be verified using a Argon2 phc string,
     /// after which the blobs field in data will be decrypted using AES256GCM with the saved key.
-    /// 
+    ///
+    /// If the password opens a decoy slot that was set up to wipe, the other slot is replaced by a filler
+    /// and [`LockedData::take_wiped`] will return true.
+    ///
     /// # Examples
     /// ```
+    /// # use fobword_core::config::LockedData;
+    /// # use fobword_core::error::DataHandleError;
     /// let mut data_lock = LockedData::new("password")?;
     /// let mut data = data_lock.unlock("password")?;
+    /// # Ok::<(), DataHandleError>(())
     /// ```
     /// # Errors
     /// [Argon / password_hash](https://docs.rs/password-hash/0.3.2/password_hash/errors/enum.Error.html)
-    /// 
+    ///
     /// [base64](https://docs.rs/base64/0.13.0/base64/enum.DecodeError.html)
-    /// 
+    ///
     /// [FromUtf8](https://doc.rust-lang.org/std/string/struct.FromUtf8Error.html)
-    /// 
+    ///
     /// [Aes-GCM](https://docs.rs/aes-gcm/0.9.4/aes_gcm/struct.Error.html)
     pub fn unlock(&mut self, password: &str) -> Result<Data, DataHandleError>
     {
-        let hash = ArgonHelper::verify_password(password, &self.salt, &self.verification_hash)?;
+        let (index, hash) = self.find_slot(password)?;
+        let slot = self.slot(index);
 
         // The hash is b64 encoded so we decode it here to make it 32 bits long to use as key for AES256GCM
         let decoded_hash = base64::decode(hash)?;
 
         // Decrypt the key used for the blobs with AES256GCM using the decoded_hash
-        let key = AesHelper::decrypt_with_key_from_b64(&decoded_hash, &self.key)?;
+        let (key, flags) = slot.open_key(&decoded_hash)?;
+        let data = slot.open_data(&key)?;
 
-        let mut data = self.data.clone();
-        for (_index, data) in data.map.iter_mut()
+        if flags & DECOY_SLOT != 0 && flags & WIPE_ON_UNLOCK != 0
         {
-            let decrypted = AesHelper::decrypt_with_key_from_b64(&key, data.blob.as_bytes())?;
-            data.blob = String::from_utf8(decrypted)?;
+            *self.slot_mut(1 - index) = KeySlot::filler()?;
+            self.wiped = true;
         }
         Ok(data)
     }
 
 
     /// Encrypt a `Data` structure using a password.
-    /// 
+    ///
     /// The password will be verified using a Argon2 phc string,
-    /// after which the blobs field in data will be encrypted using AES256GCM with a new random key.
+    /// after which the whole `data` will be encrypted using AES256GCM with a new random key.
     /// The key will be encrypted using the password after the encryption of the `data` structure.
-    /// 
+    ///
     /// # Examples
     /// ```
+    /// # use fobword_core::config::LockedData;
+    /// # use fobword_core::error::DataHandleError;
     /// let mut data_lock = LockedData::new("password")?;
     /// let mut data = data_lock.unlock("password")?;
-    /// 
+    ///
     /// data_lock.lock("password", data)?;
+    /// # Ok::<(), DataHandleError>(())
     /// ```
     /// # Errors
     /// [Argon / password_hash](https://docs.rs/password-hash/0.3.2/password_hash/errors/enum.Error.html)
-    /// 
+    ///
     /// [base64](https://docs.rs/base64/0.13.0/base64/enum.DecodeError.html)
-    /// 
+    ///
     /// [Aes-GCM](https://docs.rs/aes-gcm/0.9.4/aes_gcm/struct.Error.html)
-    pub fn lock(&mut self, password: &str, mut data: Data)  -> Result<(), DataHandleError>
+    pub fn lock(&mut self, password: &str, data: Data)  -> Result<(), DataHandleError>
     {
-        let hash = ArgonHelper::verify_password(password, &self.salt, &self.verification_hash)?;
-
-        // Create a new random key
-        let mut key = vec![0u8;32];
-        rand::rngs::OsRng.fill(key.as_mut_slice());
-
-        for (_index, data) in data.map.iter_mut()
+        let (index, hash) = self.find_slot(password)?;
+        // Older files only have one slot, give them a filler before they are written again
+        if self.alternate.is_none()
         {
-            data.blob = AesHelper::encrypt_with_key_to_b64(&key, data.blob.as_bytes())?;
+            self.alternate = Some(KeySlot::filler()?);
         }
+
         // The hash is b64 encoded so we decode it here to make it 32 bits long to use as key for AES256GCM
         let decoded_hash = base64::decode(hash)?;
-        // Encrypt the key used for encrypting the blobs so we can store it safely
-        let key = AesHelper::encrypt_with_key_to_b64(&decoded_hash, key.as_ref())?;
+        let (_, flags) = self.slot(index).open_key(&decoded_hash)?;
 
-        self.key = key.as_bytes().to_owned();
-        self.data = data;
+        // Create a new random key
+        let mut key = vec![0u8;32];
+        rand::rngs::OsRng.fill(key.as_mut_slice());
+
+        let vault = KeySlot::seal_data(&key, &data)?;
+        // Encrypt the key used for encrypting the data so we can store it safely
+        let slot = self.slot_mut(index);
+        slot.key = KeySlot::seal_key(&decoded_hash, &key, flags)?;
+        slot.vault = vault;
+        slot.data = None;
         Ok(())
     }
 
     /// Change the current password.
-    /// 
-    /// 
+    ///
+    /// Only the slot opened by `old_password` is changed.
     pub fn change_password(&mut self, old_password: &str, new_password: &str) -> Result<(), DataHandleError>
     {
-        let hash = ArgonHelper::verify_password(old_password, &self.salt, &self.verification_hash)?;
+        let (index, hash) = self.find_slot(old_password)?;
+        self.check_unused(index, new_password)?;
 
         // The hash is b64 encoded so we decode it here to make it 32 bits long to use as key for AES256GCM
         let decoded_hash = base64::decode(hash)?;
 
         // Decrypt the key used for the blobs with AES256GCM using the decoded_hash
-        let decrypted_key = AesHelper::decrypt_with_key_from_b64(&decoded_hash, &self.key)?;
+        let (decrypted_key, flags) = self.slot(index).open_key(&decoded_hash)?;
 
         // Generate a salt for the first argon2 hash
         let salt = SaltString::generate(rand::rngs::OsRng).as_str().to_owned();
@@ -214,13 +377,145 @@ impl LockedData
         let verification_hash = ArgonHelper::argon2_phc(&hashed_password.to_string(), &verification_salt)?.to_string();
 
         let decoded_hash = base64::decode(hashed_password)?;
-        let encrypted_key = AesHelper::encrypt_with_key_to_b64(&decoded_hash, decrypted_key.as_ref())?;
+        let encrypted_key = KeySlot::seal_key(&decoded_hash, &decrypted_key, flags)?;
 
-        self.key = encrypted_key.as_bytes().to_owned();
-        self.salt = salt;
-        self.verification_hash = verification_hash;
+        let slot = self.slot_mut(index);
+        slot.key = encrypted_key;
+        slot.salt = salt;
+        slot.verification_hash = verification_hash;
         Ok(())
     }
+
+    /// Set a duress password that opens an empty decoy `Data`.
+    ///
+    /// The master password has to open the real slot, the decoy replaces the other slot.
+    /// When `wipe` is set, unlocking with the duress password replaces the real slot with a filler.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::config::LockedData;
+    /// # use fobword_core::error::DataHandleError;
+    /// let mut data_lock = LockedData::new("password")?;
+    /// data_lock.set_duress_password("password", "duress", false)?;
+    ///
+    /// let decoy = data_lock.unlock("duress")?;
+    /// # Ok::<(), DataHandleError>(())
+    /// ```
+    pub fn set_duress_password(&mut self, password: &str, duress_password: &str, wipe: bool) -> Result<(), DataHandleError>
+    {
+        let index = self.find_master_slot(password)?;
+        if password == duress_password
+        {
+            return Err(DataHandleError::LockedData("the duress password must differ from the master password".to_string()));
+        }
+
+        let flags = if wipe { DECOY_SLOT | WIPE_ON_UNLOCK } else { DECOY_SLOT };
+        let decoy = KeySlot::new(duress_password, flags)?;
+        if index == 0
+        {
+            self.alternate = Some(decoy);
+        }
+        else
+        {
+            self.primary = decoy;
+        }
+        Ok(())
+    }
+
+    /// Remove the duress password, replacing the decoy slot with a filler.
+    pub fn remove_duress_password(&mut self, password: &str) -> Result<(), DataHandleError>
+    {
+        let index = self.find_master_slot(password)?;
+        if index == 0
+        {
+            self.alternate = Some(KeySlot::filler()?);
+        }
+        else
+        {
+            self.primary = KeySlot::filler()?;
+        }
+        Ok(())
+    }
+
+    /// Check that the password is the master password, the duress password is refused.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::config::LockedData;
+    /// # use fobword_core::error::DataHandleError;
+    /// let mut data_lock = LockedData::new("password")?;
+    /// data_lock.set_duress_password("password", "duress", false)?;
+    ///
+    /// assert!(data_lock.check_master_password("password").is_ok());
+    /// assert!(data_lock.check_master_password("duress").is_err());
+    /// # Ok::<(), DataHandleError>(())
+    /// ```
+    pub fn check_master_password(&self, password: &str) -> Result<(), DataHandleError>
+    {
+        self.find_master_slot(password).map(|_| ())
+    }
+
+    /// Returns true once after an unlock wiped the other slot, so the caller can write the change to disk.
+    pub fn take_wiped(&mut self) -> bool
+    {
+        std::mem::replace(&mut self.wiped, false)
+    }
+
+    /// Find the slot opened by the password, returning its index and the argon2 hash.
+    ///
+    /// Both slots are always verified so the time taken does not reveal which slot was opened.
+    fn find_slot(&self, password: &str) -> Result<(usize, String), DataHandleError>
+    {
+        let primary = self.primary.verify(password);
+        let alternate = self.alternate.as_ref().map(|slot| slot.verify(password));
+        match (primary, alternate)
+        {
+            (Ok(hash), _) => Ok((0, hash)),
+            (_, Some(Ok(hash))) => Ok((1, hash)),
+            (Err(error), _) => Err(error),
+        }
+    }
+
+    /// Find the slot opened by the master password, the decoy slot is an error.
+    fn find_master_slot(&self, password: &str) -> Result<usize, DataHandleError>
+    {
+        let (index, hash) = self.find_slot(password)?;
+        let (_, flags) = self.slot(index).open_key(&base64::decode(hash)?)?;
+        if flags & DECOY_SLOT != 0
+        {
+            return Err(DataHandleError::LockedData("the master password is required".to_string()));
+        }
+        Ok(index)
+    }
+
+    /// Return an error if the slot other than `index` is opened by the password.
+    fn check_unused(&self, index: usize, password: &str) -> Result<(), DataHandleError>
+    {
+        let other = if index == 0 { self.alternate.as_ref() } else { Some(&self.primary) };
+        match other.map(|slot| slot.verify(password))
+        {
+            Some(Ok(_)) => Err(DataHandleError::LockedData("password is already in use".to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    fn slot(&self, index: usize) -> &KeySlot
+    {
+        match (index, &self.alternate)
+        {
+            (1, Some(slot)) => slot,
+            _ => &self.primary,
+        }
+    }
+
+    fn slot_mut(&mut self, index: usize) -> &mut KeySlot
+    {
+        match (index, &mut self.alternate)
+        {
+            (1, Some(slot)) => slot,
+            _ => &mut self.primary,
+        }
+    }
 }
 #[derive(Debug, Serialize, Deserialize, Clone)]
 /// Wrapper around a `HashMap<String, DataInformation>`.
@@ -236,10 +531,13 @@ impl Data
     /// # Examples
     ///
     /// ```
+    /// # use fobword_core::config::{Data, DataInformation, SymbolLevel};
+    /// # use fobword_core::error::DataHandleError;
     /// let mut data = Data::new();
     /// 
     /// data.generate(String::from("website"), None, 8, SymbolLevel::Symbols)?;
-    /// data.insert(String::from("other_website"), DataInformation::new(None, None, String::from("password")))?;
+    /// data.insert(String::from("other_website"), DataInformation::new(None, None, String::from("password")));
+    /// # Ok::<(), DataHandleError>(())
     /// ``` 
     pub fn new() -> Data
     {
@@ -255,8 +553,11 @@ impl Data
     /// The hint will be "Randomly generated password".
     /// # Examples
     /// ```
+    /// # use fobword_core::config::{Data, SymbolLevel};
+    /// # use fobword_core::error::DataHandleError;
     /// let mut data = Data::new();
-    /// data.generate(String::from)
+    /// data.generate(String::from("website"), Some(String::from("work account")), 16, SymbolLevel::Numbers)?;
+    /// # Ok::<(), DataHandleError>(())
     /// ```
     pub fn generate(&mut self, index: String, comment: Option<String>, password_length: u8, symbol_level: SymbolLevel) -> Result<(), DataHandleError>
     {
@@ -264,7 +565,9 @@ impl Data
 
         let hint = Some("Randomly generated password".to_string());
 
-        let information = DataInformation { hint, comment, blob: password };
+        // A regenerated password is typed on the same host as before
+        let host = self.get(&index).and_then(|information| information.host);
+        let information = DataInformation { hint, comment, blob: password, host };
 
         self.insert(index, information);
         Ok(())
@@ -281,6 +584,7 @@ impl Data
     ///
     /// # Examples
     /// ```
+    /// # use fobword_core::config::{Data, SymbolLevel};
     /// let lowercase_5_letter_password = Data::generate_password(5, SymbolLevel::LowercaseAscii);
     /// let symbols_20_letter_password = Data::generate_password(20, SymbolLevel::Symbols);
     /// ```
@@ -306,12 +610,13 @@ impl Data
     /// # Example
     /// 
     /// ```
+    /// # use fobword_core::config::{Data, DataInformation};
     /// let mut data = Data::new();
     /// let information = DataInformation::new(None, None, String::from("some_password"));
     /// assert_eq!(None, data.insert(String::from("Website"), information));
     ///
     /// let new_information = DataInformation::new(None, None, String::from("new_password"));
-    /// let result = Some(DataInformation { hint: None, comment:None, blob: String::from("some_password")})
+    /// let result = Some(DataInformation { hint: None, comment:None, blob: String::from("some_password"), host: None });
     /// assert_eq!(result, data.insert(String::from("Website"), new_information));
     /// ```
     pub fn insert(&mut self, name: String, information: DataInformation) -> Option<DataInformation>
@@ -328,11 +633,12 @@ impl Data
     /// # Examples
     ///
     /// ```
+    /// # use fobword_core::config::{Data, DataInformation};
     /// let mut data = Data::new();
     /// let information = DataInformation::new(None, None, String::from("some_password"));
     /// data.insert(String::from("Website"), information);
     ///
-    /// assert_eq!(data.remove("Website"), Some(DataInformation { hint: None, comment:None, blob: String::from("some_password")}));
+    /// assert_eq!(data.remove("Website"), Some(DataInformation { hint: None, comment:None, blob: String::from("some_password"), host: None }));
     /// assert_eq!(data.remove("Website"), None);
     /// ```
     pub fn remove(&mut self, name: &str) -> Option<DataInformation>
@@ -348,11 +654,12 @@ impl Data
     /// # Examples
     ///
     /// ```
+    /// # use fobword_core::config::{Data, DataInformation};
     /// let mut data = Data::new();
     /// let information = DataInformation::new(None, None, String::from("some_password"));
     /// data.insert(String::from("Website"), information);
     ///
-    /// assert_eq!(data.get("Website"), Some(DataInformation { hint: None, comment:None, blob: String::from("some_password")}));
+    /// assert_eq!(data.get("Website"), Some(&DataInformation { hint: None, comment:None, blob: String::from("some_password"), host: None }));
     /// ```
     pub fn get(&self, name: &str) -> Option<&DataInformation>
     {
@@ -363,14 +670,17 @@ impl Data
 
 /// Holds the information for the data struct.
 ///
-/// `hint` and `comment` are optional, descriptive fields, and will stay as plaintext in the configuration file.
-/// The `blob` field will be encrypted using Aes256Gcm and converted to a b64 string for conversion purposes.
+/// `hint` and `comment` are optional, descriptive fields.
+/// They are encrypted together with the `blob` field when the `Data` is locked, see [`LockedData`].
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DataInformation
 {
     pub hint: Option<String>,
     pub comment: Option<String>,
     pub blob: String,
+    /// The pace the password is typed at, the default pace if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<HostProfile>,
 }
 
 impl DataInformation
@@ -379,11 +689,12 @@ impl DataInformation
     /// 
     /// # Examples
     /// ```
+    /// # use fobword_core::config::DataInformation;
     /// let data = DataInformation::new(None, None, String::from("Maybe_Some_password"));
     /// ```
     pub fn new(hint: Option<String>, comment: Option<String>, blob: String) -> DataInformation
     {
-        DataInformation { hint, comment, blob}
+        DataInformation { hint, comment, blob, host: None }
     }
 }
 
@@ -407,7 +718,7 @@ mod configtests
         let information = DataInformation::new(None, None, String::from("some_password"));
         assert_eq!(None, data.insert(String::from("Website"), information));
         let new_information = DataInformation::new(None, None, String::from("new_password"));
-        let result = Some(DataInformation { hint: None, comment:None, blob: String::from("some_password")});
+        let result = Some(DataInformation { hint: None, comment:None, blob: String::from("some_password"), host: None });
         assert_eq!(result, data.insert(String::from("Website"), new_information));
     }
 
@@ -418,9 +729,144 @@ mod configtests
         let information = DataInformation::new(None, None, String::from("some_password"));
         data.insert(String::from("Website"), information);
     
-        assert_eq!(data.remove("Website"), Some(DataInformation { hint: None, comment:None, blob: String::from("some_password")}));
+        assert_eq!(data.remove("Website"), Some(DataInformation { hint: None, comment:None, blob: String::from("some_password"), host: None }));
         assert_eq!(data.remove("Website"), None);
     }
+
+    #[test]
+    fn test_symbol_level_from_name()
+    {
+        assert_eq!(Some(SymbolLevel::LowercaseAscii), SymbolLevel::from_name("1"));
+        assert_eq!(Some(SymbolLevel::UppercaseAscii), SymbolLevel::from_name("Upper"));
+        assert_eq!(Some(SymbolLevel::Numbers), SymbolLevel::from_name(" numbers "));
+        assert_eq!(Some(SymbolLevel::Symbols), SymbolLevel::from_name("4"));
+        assert_eq!(None, SymbolLevel::from_name("5"));
+        assert_eq!(None, SymbolLevel::from_name(""));
+    }
+
+    #[test]
+    fn test_regenerate_keeps_host()
+    {
+        let mut data = Data::new();
+        let mut information = DataInformation::new(None, None, String::from("some_password"));
+        information.host = Some(HostProfile::Bios);
+        data.insert(String::from("Website"), information);
+        data.generate(String::from("Website"), None, 12, SymbolLevel::Symbols).unwrap();
+        assert_eq!(Some(HostProfile::Bios), data.get("Website").and_then(|information| information.host));
+    }
+
+    #[test]
+    fn test_duress_password_opens_decoy()
+    {
+        let mut locked_data = LockedData::new("password").unwrap();
+        let mut data = locked_data.unlock("password").unwrap();
+        data.insert(String::from("Website"), DataInformation::new(None, None, String::from("some_password")));
+        locked_data.lock("password", data).unwrap();
+
+        locked_data.set_duress_password("password", "duress", false).unwrap();
+
+        let decoy = locked_data.unlock("duress").unwrap();
+        assert_eq!(decoy.get("Website"), None);
+        assert!(!locked_data.take_wiped());
+
+        let data = locked_data.unlock("password").unwrap();
+        assert_eq!(data.get("Website").map(|i| i.blob.as_str()), Some("some_password"));
+    }
+
+    #[test]
+    fn test_slots_look_the_same()
+    {
+        let mut locked_data = LockedData::new("password").unwrap();
+        locked_data.set_duress_password("password", "duress", false).unwrap();
+        let mut decoy = locked_data.unlock("duress").unwrap();
+        decoy.insert(String::from("Website"), DataInformation::new(Some(String::from("my hint")), None, String::from("some_password")));
+        locked_data.lock("duress", decoy).unwrap();
+
+        let without_decoy = LockedData::new("password").unwrap();
+        for locked in [&locked_data, &without_decoy].iter()
+        {
+            assert_eq!(locked.primary.vault.len(), locked.alternate.as_ref().unwrap().vault.len());
+            assert_eq!(locked.primary.key.len(), locked.alternate.as_ref().unwrap().key.len());
+        }
+        assert_eq!(locked_data.primary.vault.len(), without_decoy.primary.vault.len());
+
+        let yaml = locked_data.to_yaml().unwrap();
+        assert!(!yaml.contains("Website") && !yaml.contains("my hint"));
+        let mut loaded = LockedData::from_yaml(&yaml).unwrap();
+        assert_eq!(Some("my hint"), loaded.unlock("duress").unwrap().get("Website").and_then(|i| i.hint.as_deref()));
+    }
+
+    #[test]
+    fn test_damaged_vault_does_not_load()
+    {
+        let locked_data = LockedData::new("password").unwrap();
+        let yaml = locked_data.to_yaml().unwrap();
+        let vault = &locked_data.primary.vault;
+        let damaged = yaml.replacen(vault.as_str(), &format!("{}*{}", &vault[..10], &vault[11..]), 1);
+
+        assert!(matches!(LockedData::from_yaml(&damaged), Err(DataHandleError::B64ParseError(_))));
+    }
+
+    #[test]
+    fn test_legacy_data_loads()
+    {
+        let locked_data = LockedData::new("password").unwrap();
+        let (_, hash) = locked_data.find_slot("password").unwrap();
+        let (key, _) = locked_data.primary.open_key(&base64::decode(hash).unwrap()).unwrap();
+
+        // Files from before the whole data was encrypted have a single slot, with only the blobs encrypted
+        let blob = AesHelper::encrypt_with_key_to_b64(&key, "some_password".as_bytes()).unwrap();
+        let slot = &locked_data.primary;
+        let yaml = format!("verification_hash: \"{}\"\nsalt: {}\nkey: {}\ndata:\n  map:\n    Website:\n      hint: a hint\n      comment: ~\n      blob: {}\n",
+            slot.verification_hash, slot.salt, String::from_utf8(slot.key.clone()).unwrap(), blob);
+
+        let mut loaded = LockedData::from_yaml(&yaml).unwrap();
+        let data = loaded.unlock("password").unwrap();
+        assert_eq!(Some("some_password"), data.get("Website").map(|i| i.blob.as_str()));
+
+        // Written again in the new format
+        loaded.lock("password", data).unwrap();
+        let yaml = loaded.to_yaml().unwrap();
+        assert!(!yaml.contains("Website") && !yaml.contains("a hint"));
+        assert_eq!(Some("some_password"), LockedData::from_yaml(&yaml).unwrap().unlock("password").unwrap().get("Website").map(|i| i.blob.as_str()));
+    }
+
+    #[test]
+    fn test_duress_password_wipes_real_data()
+    {
+        let mut locked_data = LockedData::new("password").unwrap();
+        locked_data.set_duress_password("password", "duress", true).unwrap();
+
+        locked_data.unlock("duress").unwrap();
+        assert!(locked_data.take_wiped());
+        assert!(locked_data.unlock("password").is_err());
+        assert!(locked_data.unlock("duress").is_ok());
+    }
+
+    #[test]
+    fn test_duress_password_requires_master_password()
+    {
+        let mut locked_data = LockedData::new("password").unwrap();
+        locked_data.set_duress_password("password", "duress", false).unwrap();
+
+        assert!(locked_data.set_duress_password("duress", "other", false).is_err());
+        assert!(locked_data.set_duress_password("password", "password", false).is_err());
+        assert!(locked_data.change_password("password", "duress").is_err());
+    }
+
+    #[test]
+    fn test_check_master_password()
+    {
+        let mut locked_data = LockedData::new("password").unwrap();
+        locked_data.set_duress_password("password", "duress", true).unwrap();
+
+        assert!(locked_data.check_master_password("password").is_ok());
+        assert!(matches!(locked_data.check_master_password("duress"), Err(DataHandleError::LockedData(_))));
+        assert!(matches!(locked_data.check_master_password("wrong"), Err(DataHandleError::ArgonError(_))));
+        // Checking the duress password must not wipe the real slot
+        assert!(!locked_data.take_wiped());
+        assert!(locked_data.unlock("password").is_ok());
+    }
 }
 
 /// A helper struct for Aes2Gcm encryption and decryption.
@@ -443,7 +889,7 @@ impl AesHelper
 
     fn decrypt_with_key_from_b64<'msg, 'aad>(key: &[u8], encryptedtext: &[u8]) -> Result<Vec<u8>, DataHandleError>
     {
-        let blob = base64::decode(encryptedtext).expect("remove me");
+        let blob = base64::decode(encryptedtext)?;
         AesHelper::decrypt_with_key(key, blob.as_ref())
     }
 
@@ -499,6 +945,7 @@ impl ArgonHelper
 }
 
 /// Symbollevel indicates what type of characters will be in a generated password.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SymbolLevel
 {
     /// Use only lowercase ascii characters in the password
@@ -509,4 +956,28 @@ pub enum SymbolLevel
     Numbers = 62,
     /// Use numbers, symbols and upper and lowercase ascii characters in the password
     Symbols = 77,
-}
\ No newline at end of file
+}
+
+impl SymbolLevel
+{
+    /// Parse a symbol level as typed by the user, either its number from 1 to 4 or its name.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::config::SymbolLevel;
+    /// assert_eq!(Some(SymbolLevel::Numbers), SymbolLevel::from_name("3"));
+    /// assert_eq!(Some(SymbolLevel::Symbols), SymbolLevel::from_name("symbols"));
+    /// assert_eq!(None, SymbolLevel::from_name("emoji"));
+    /// ```
+    pub fn from_name(name: &str) -> Option<SymbolLevel>
+    {
+        match name.trim().to_lowercase().as_str()
+        {
+            "1" | "lower" | "lowercase" => Some(SymbolLevel::LowercaseAscii),
+            "2" | "upper" | "uppercase" => Some(SymbolLevel::UppercaseAscii),
+            "3" | "numbers" => Some(SymbolLevel::Numbers),
+            "4" | "symbols" => Some(SymbolLevel::Symbols),
+            _ => None,
+        }
+    }
+}
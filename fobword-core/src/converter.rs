@@ -1,18 +1,19 @@
-use std::{collections::HashMap, fs::OpenOptions, io::Read};
+use std::{collections::HashMap, fs::OpenOptions, io::Read, path::Path};
 use std::io::{Error, ErrorKind};
 use serde::{ Serialize, Deserialize };
 /// A struct that holds a map which can be used to convert between raw keyboard codes and Keypress enum variants.
 ///
 /// # Example
 /// ```
+/// # use fobword_core::converter::{Converter, Key, Modifier};
 /// let converter = Converter::default();
 ///
-/// let modifier_key = Modifier::NoModifier;
+/// let modifier_key = Modifier::NONE;
 /// let raw_key_code_a = 0x04u8;
-/// assert_eq!(Keypress::Character('a'), converter.convert_rawinput(&modifier_key, &raw_key_code_a));
+/// assert_eq!(Key::Char('a'), converter.get_key(&(modifier_key, raw_key_code_a)));
 ///
-/// let keypress = Keypress::Character('Z');
-/// assert_eq!((Modifier::Shift, 0x1du8), converter.convert_keypress(&keypress));
+/// let keypress = Key::Char('Z');
+/// assert_eq!((Modifier::LEFT_SHIFT, 0x1du8), converter.get_raw(&keypress));
 /// ```
 #[derive(Debug)]
 pub struct Converter
@@ -30,6 +31,7 @@ impl Converter
     ///
     /// # Examples
     /// ```
+    /// # use fobword_core::converter::Converter;
     /// let mut converter = Converter::new();
     /// ```
     pub fn new() -> Converter
@@ -39,7 +41,11 @@ impl Converter
         Converter { input_map, output_map }
     }
 
-    pub fn from_paths(input: &str, output: &str) -> Result<Converter, Error>
+    /// Constructs a Converter from an input and an output layout file.
+    ///
+    /// The layouts are RON lists of (Key, (Modifier, Key number)) pairs, see `qwerty-layout.txt`.
+    /// Named keys like `Escape` or `F(13)` that a layout leaves out get their default HID usage.
+    pub fn from_paths<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> Result<Converter, Error>
     {
         let mut input_string = String::new();
         let mut input_file = OpenOptions::new().read(true).open(input)?;
@@ -52,7 +58,9 @@ impl Converter
         output_file.read_to_string(&mut output_string)?;
         let output_vector: Vec<(Key, (Modifier, u8))> = ron::from_str(&output_string).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
         let output_map = output_vector.iter().cloned().collect::<HashMap<Key, (Modifier, u8)>>();
-        Ok( Converter { input_map, output_map } )
+        let mut converter = Converter { input_map, output_map };
+        converter.add_named_keys();
+        Ok(converter)
     }
 
     /// Constructs a Converter with  
@@ -60,147 +68,151 @@ impl Converter
     ///
     /// # Examples
     /// ```
+    /// # use fobword_core::converter::Converter;
     /// let mut converter = Converter::default();
     /// ```
     pub fn default() -> Converter
     {
-        use Key::{ Char, Enter, F, Backspace };
-        use Modifier::{ NoModifier, Shift };
+        use Key::{ Char, Enter, Backspace };
+        const NONE: Modifier = Modifier::NONE;
+        const SHIFT: Modifier = Modifier::LEFT_SHIFT;
         let pairs: Vec<(Key, (Modifier, u8))> = 
         [
             // Lowercase characters
-            (Char('a'), (NoModifier, 0x04u8)),
-            (Char('b'), (NoModifier, 0x05u8)),
-            (Char('c'), (NoModifier, 0x06u8)),
-            (Char('d'), (NoModifier, 0x07u8)),
-            (Char('e'), (NoModifier, 0x08u8)),
-            (Char('f'), (NoModifier, 0x09u8)),
-            (Char('g'), (NoModifier, 0x0au8)),
-            (Char('h'), (NoModifier, 0x0bu8)),
-            (Char('i'), (NoModifier, 0x0cu8)),
-            (Char('j'), (NoModifier, 0x0du8)),
-            (Char('k'), (NoModifier, 0x0eu8)),
-            (Char('l'), (NoModifier, 0x0fu8)),
-            (Char('m'), (NoModifier, 0x10u8)),
-            (Char('n'), (NoModifier, 0x11u8)),
-            (Char('o'), (NoModifier, 0x12u8)),
-            (Char('p'), (NoModifier, 0x13u8)),
-            (Char('q'), (NoModifier, 0x14u8)),
-            (Char('r'), (NoModifier, 0x15u8)),
-            (Char('s'), (NoModifier, 0x16u8)),
-            (Char('t'), (NoModifier, 0x17u8)),
-            (Char('u'), (NoModifier, 0x18u8)),
-            (Char('v'), (NoModifier, 0x19u8)),
-            (Char('w'), (NoModifier, 0x1au8)),
-            (Char('x'), (NoModifier, 0x1bu8)),
-            (Char('y'), (NoModifier, 0x1cu8)),
-            (Char('z'), (NoModifier, 0x1du8)),
+            (Char('a'), (NONE, 0x04u8)),
+            (Char('b'), (NONE, 0x05u8)),
+            (Char('c'), (NONE, 0x06u8)),
+            (Char('d'), (NONE, 0x07u8)),
+            (Char('e'), (NONE, 0x08u8)),
+            (Char('f'), (NONE, 0x09u8)),
+            (Char('g'), (NONE, 0x0au8)),
+            (Char('h'), (NONE, 0x0bu8)),
+            (Char('i'), (NONE, 0x0cu8)),
+            (Char('j'), (NONE, 0x0du8)),
+            (Char('k'), (NONE, 0x0eu8)),
+            (Char('l'), (NONE, 0x0fu8)),
+            (Char('m'), (NONE, 0x10u8)),
+            (Char('n'), (NONE, 0x11u8)),
+            (Char('o'), (NONE, 0x12u8)),
+            (Char('p'), (NONE, 0x13u8)),
+            (Char('q'), (NONE, 0x14u8)),
+            (Char('r'), (NONE, 0x15u8)),
+            (Char('s'), (NONE, 0x16u8)),
+            (Char('t'), (NONE, 0x17u8)),
+            (Char('u'), (NONE, 0x18u8)),
+            (Char('v'), (NONE, 0x19u8)),
+            (Char('w'), (NONE, 0x1au8)),
+            (Char('x'), (NONE, 0x1bu8)),
+            (Char('y'), (NONE, 0x1cu8)),
+            (Char('z'), (NONE, 0x1du8)),
 
             // Uppercase characters
-            (Char('A'), (Shift, 0x04u8)),
-            (Char('B'), (Shift, 0x05u8)),
-            (Char('C'), (Shift, 0x06u8)),
-            (Char('D'), (Shift, 0x07u8)),
-            (Char('E'), (Shift, 0x08u8)),
-            (Char('F'), (Shift, 0x09u8)),
-            (Char('G'), (Shift, 0x0au8)),
-            (Char('H'), (Shift, 0x0bu8)),
-            (Char('I'), (Shift, 0x0cu8)),
-            (Char('J'), (Shift, 0x0du8)),
-            (Char('K'), (Shift, 0x0eu8)),
-            (Char('L'), (Shift, 0x0fu8)),
-            (Char('M'), (Shift, 0x10u8)),
-            (Char('N'), (Shift, 0x11u8)),
-            (Char('O'), (Shift, 0x12u8)),
-            (Char('P'), (Shift, 0x13u8)),
-            (Char('Q'), (Shift, 0x14u8)),
-            (Char('R'), (Shift, 0x15u8)),
-            (Char('S'), (Shift, 0x16u8)),
-            (Char('T'), (Shift, 0x17u8)),
-            (Char('U'), (Shift, 0x18u8)),
-            (Char('V'), (Shift, 0x19u8)),
-            (Char('W'), (Shift, 0x1au8)),
-            (Char('X'), (Shift, 0x1bu8)),
-            (Char('Y'), (Shift, 0x1cu8)),
-            (Char('Z'), (Shift, 0x1du8)),
+            (Char('A'), (SHIFT, 0x04u8)),
+            (Char('B'), (SHIFT, 0x05u8)),
+            (Char('C'), (SHIFT, 0x06u8)),
+            (Char('D'), (SHIFT, 0x07u8)),
+            (Char('E'), (SHIFT, 0x08u8)),
+            (Char('F'), (SHIFT, 0x09u8)),
+            (Char('G'), (SHIFT, 0x0au8)),
+            (Char('H'), (SHIFT, 0x0bu8)),
+            (Char('I'), (SHIFT, 0x0cu8)),
+            (Char('J'), (SHIFT, 0x0du8)),
+            (Char('K'), (SHIFT, 0x0eu8)),
+            (Char('L'), (SHIFT, 0x0fu8)),
+            (Char('M'), (SHIFT, 0x10u8)),
+            (Char('N'), (SHIFT, 0x11u8)),
+            (Char('O'), (SHIFT, 0x12u8)),
+            (Char('P'), (SHIFT, 0x13u8)),
+            (Char('Q'), (SHIFT, 0x14u8)),
+            (Char('R'), (SHIFT, 0x15u8)),
+            (Char('S'), (SHIFT, 0x16u8)),
+            (Char('T'), (SHIFT, 0x17u8)),
+            (Char('U'), (SHIFT, 0x18u8)),
+            (Char('V'), (SHIFT, 0x19u8)),
+            (Char('W'), (SHIFT, 0x1au8)),
+            (Char('X'), (SHIFT, 0x1bu8)),
+            (Char('Y'), (SHIFT, 0x1cu8)),
+            (Char('Z'), (SHIFT, 0x1du8)),
 
             // Numbers
-            (Char('1'), (NoModifier, 0x1eu8)),
-            (Char('2'), (NoModifier, 0x1fu8)),
-            (Char('3'), (NoModifier, 0x20u8)),
-            (Char('4'), (NoModifier, 0x21u8)),
-            (Char('5'), (NoModifier, 0x22u8)),
-            (Char('6'), (NoModifier, 0x23u8)),
-            (Char('7'), (NoModifier, 0x24u8)),
-            (Char('8'), (NoModifier, 0x25u8)),
-            (Char('9'), (NoModifier, 0x26u8)),
-            (Char('0'), (NoModifier, 0x27u8)),
+            (Char('1'), (NONE, 0x1eu8)),
+            (Char('2'), (NONE, 0x1fu8)),
+            (Char('3'), (NONE, 0x20u8)),
+            (Char('4'), (NONE, 0x21u8)),
+            (Char('5'), (NONE, 0x22u8)),
+            (Char('6'), (NONE, 0x23u8)),
+            (Char('7'), (NONE, 0x24u8)),
+            (Char('8'), (NONE, 0x25u8)),
+            (Char('9'), (NONE, 0x26u8)),
+            (Char('0'), (NONE, 0x27u8)),
 
             // Symbols
-            (Char('!'), (Shift, 0x1eu8)),
-            (Char('@'), (Shift, 0x1fu8)),
-            (Char('#'), (Shift, 0x20u8)),
-            (Char('$'), (Shift, 0x21u8)),
-            (Char('%'), (Shift, 0x22u8)),
-            (Char('^'), (Shift, 0x23u8)),
-            (Char('&'), (Shift, 0x24u8)),
-            (Char('*'), (Shift, 0x25u8)),
-            (Char('('), (Shift, 0x26u8)),
-            (Char(')'), (Shift, 0x27u8)),
-
-            (Char('\t'), (NoModifier, 0x2bu8)),
-            (Char(' '), (NoModifier, 0x2cu8)),
-            (Char('-'), (NoModifier, 0x2du8)),
-            (Char('_'), (Shift, 0x2du8)),
-            (Char('='), (NoModifier, 0x2eu8)),
-            (Char('+'), (Shift, 0x2eu8)),
-            (Char('['), (NoModifier, 0x2fu8)),
-            (Char('{'), (Shift, 0x2fu8)),
-            (Char(']'), (NoModifier, 0x30u8)),
-            (Char('}'), (Shift, 0x30u8)),
-            (Char('\\'), (NoModifier, 0x31u8)),
-            (Char('|'), (Shift, 0x31u8)),
-            (Char(';'), (NoModifier, 0x33u8)),
-            (Char(':'), (Shift, 0x33u8)),
-            (Char('\''), (NoModifier, 0x34u8)),
-            (Char('\"'), (Shift, 0x34u8)),
-            (Char('`'), (NoModifier, 0x35u8)),
-            (Char('~'), (Shift, 0x35u8)),
-            (Char(','), (NoModifier, 0x36u8)),
-            (Char('<'), (Shift, 0x36u8)),
-            (Char('.'), (NoModifier, 0x37u8)),
-            (Char('>'), (Shift, 0x37u8)),
-            (Char('/'), (NoModifier, 0x38u8)),
-            (Char('?'), (Shift, 0x38u8)),
-
-            // The F keys
-            (F(1), (NoModifier, 0x3au8)),
-            (F(2), (NoModifier, 0x3bu8)),
-            (F(3), (NoModifier, 0x3cu8)),
-            (F(4), (NoModifier, 0x3du8)),
-            (F(5), (NoModifier, 0x3eu8)),
-            (F(6), (NoModifier, 0x3fu8)),
-            (F(7), (NoModifier, 0x40u8)),
-            (F(8), (NoModifier, 0x41u8)),
-            (F(9), (NoModifier, 0x42u8)),
-            (F(10), (NoModifier, 0x43u8)),
-            (F(11), (NoModifier, 0x44u8)),
-            (F(12), (NoModifier, 0x45u8)),
-
-            (Enter, (NoModifier, 0x28u8)),
-            (Backspace, (NoModifier, 0x2Au8))
+            (Char('!'), (SHIFT, 0x1eu8)),
+            (Char('@'), (SHIFT, 0x1fu8)),
+            (Char('#'), (SHIFT, 0x20u8)),
+            (Char('$'), (SHIFT, 0x21u8)),
+            (Char('%'), (SHIFT, 0x22u8)),
+            (Char('^'), (SHIFT, 0x23u8)),
+            (Char('&'), (SHIFT, 0x24u8)),
+            (Char('*'), (SHIFT, 0x25u8)),
+            (Char('('), (SHIFT, 0x26u8)),
+            (Char(')'), (SHIFT, 0x27u8)),
+
+            (Char('\t'), (NONE, 0x2bu8)),
+            (Char(' '), (NONE, 0x2cu8)),
+            (Char('-'), (NONE, 0x2du8)),
+            (Char('_'), (SHIFT, 0x2du8)),
+            (Char('='), (NONE, 0x2eu8)),
+            (Char('+'), (SHIFT, 0x2eu8)),
+            (Char('['), (NONE, 0x2fu8)),
+            (Char('{'), (SHIFT, 0x2fu8)),
+            (Char(']'), (NONE, 0x30u8)),
+            (Char('}'), (SHIFT, 0x30u8)),
+            (Char('\\'), (NONE, 0x31u8)),
+            (Char('|'), (SHIFT, 0x31u8)),
+            (Char(';'), (NONE, 0x33u8)),
+            (Char(':'), (SHIFT, 0x33u8)),
+            (Char('\''), (NONE, 0x34u8)),
+            (Char('\"'), (SHIFT, 0x34u8)),
+            (Char('`'), (NONE, 0x35u8)),
+            (Char('~'), (SHIFT, 0x35u8)),
+            (Char(','), (NONE, 0x36u8)),
+            (Char('<'), (SHIFT, 0x36u8)),
+            (Char('.'), (NONE, 0x37u8)),
+            (Char('>'), (SHIFT, 0x37u8)),
+            (Char('/'), (NONE, 0x38u8)),
+            (Char('?'), (SHIFT, 0x38u8)),
+
+            (Enter, (NONE, 0x28u8)),
+            (Backspace, (NONE, 0x2Au8))
         ].iter().cloned().collect();
 
         let input_map = pairs.iter().cloned().map(|(k, c)| (c, k)).collect();
         let output_map = pairs.iter().cloned().collect();
-        Converter { input_map, output_map }
+        let mut converter = Converter { input_map, output_map };
+        converter.add_named_keys();
+        converter
+    }
+
+    /// Add the F keys, navigation, editing, keypad and lock keys, unless the layout already has the key or its code.
+    fn add_named_keys(&mut self)
+    {
+        for (key, code) in Key::named_keys()
+        {
+            if !self.output_map.contains_key(&key) && !self.input_map.contains_key(&(Modifier::NONE, code))
+            {
+                self.add_type(key, Modifier::NONE, code);
+            }
+        }
     }
 
 
     /// Add a new Macro keypress to the converter with the given raw inputs.
+    ///
+    /// The macro key does not care about the side of its modifiers, it is stored with the left ones.
     pub fn add_macro(&mut self, modifier: Modifier, raw_key: u8)
     {
-        self.add_type(Key::Macro, modifier, raw_key);
+        self.add_type(Key::Macro, modifier.to_left(), raw_key);
     }
 
     /// Add a keypress-raw input (Modifier key, u8 keycode) pair into the Converter 
@@ -210,10 +222,15 @@ impl Converter
         self.output_map.insert(keypress, (modifier, raw_key));
     }
 
+    /// The key for a modifier and key code.
+    ///
+    /// Right hand modifiers the layout has no keys for act like the left ones, so right shift still types capitals.
     pub fn get_key(&self, k: &(Modifier, u8)) -> Key
     {
         // Needs to return an owned value for undefined keys
-        self.input_map.get(k).map_or_else(|| Key::Undefined(k.0, k.1), |v| v.to_owned())
+        self.input_map.get(k)
+            .or_else(|| self.input_map.get(&(k.0.to_left(), k.1)))
+            .map_or_else(|| Key::Undefined(k.0, k.1), |v| v.to_owned())
     }
 
     pub fn get_raw(&self, k: &Key) -> (Modifier, u8)
@@ -224,96 +241,354 @@ impl Converter
             &Key::Undefined(m, c) =>
             {
                 // Try to find the key as if it had no modifier
-                let no_mod_key = self.input_map.get(&(Modifier::NoModifier, c));
+                let no_mod_key = self.input_map.get(&(Modifier::NONE, c));
                 match no_mod_key
                 {
                     // If they key without modifier exists, get the converted code, and add the original modifier
-                    Some(k) => self.output_map.get(k).map_or_else(|| (Modifier::NoModifier, 0), |v| (m, v.1).to_owned()),
+                    Some(k) => self.output_map.get(k).map_or_else(|| (Modifier::NONE, 0), |v| (m, v.1).to_owned()),
                     // If it still can't be found return the raw codes and it will default to your computers mapping
                     None => (m, c),
                 }
             },
-            _ => self.output_map.get(k).map_or_else(|| (Modifier::NoModifier, 0), |v| v.to_owned())
+            _ => self.output_map.get(k).map_or_else(|| (Modifier::NONE, 0), |v| v.to_owned())
         }
     }
 }
 
 /// Keyboard modifier input values
 ///
-/// Type Modifier represents the pressed combinations of the modifier keys on the keyboard (ctrl, shift, alt, gui or none).
-#[derive(Debug, PartialEq, Clone, Copy, Hash, Eq, Deserialize, Serialize)]
-pub enum Modifier
+/// Type Modifier holds the eight modifier bits of a HID report, left and right keys are kept apart.
+/// Modifiers are combined with `|`, `Modifier::LEFT_SHIFT | Modifier::RIGHT_ALT` is Shift with AltGr.
+///
+/// In layout files a modifier is written with the names of the old enum, like `NoModifier`, `Shift` or `CtrlAlt`,
+/// other combinations are written as a list of keys, like `Keys(LeftShift, RightAlt)`.
+///
+/// # Examples
+/// ```
+/// # use fobword_core::converter::Modifier;
+/// let modifier = Modifier::from(0x42);
+/// assert!(modifier.contains(Modifier::RIGHT_ALT));
+/// assert_eq!(Modifier::LEFT_SHIFT | Modifier::LEFT_ALT, modifier.to_left());
+/// ```
+#[derive(PartialEq, Clone, Copy, Hash, Eq, Default)]
+pub struct Modifier(u8);
+
+impl Modifier
 {
     /// No modifier key is pressed
-    NoModifier = 0,
-    /// Control modifier key is pressed
-    Ctrl = 1,
-    /// Shift modifier key is pressed
-    Shift = 2,
-    /// Control and shift modifier keys are pressed
-    CtrlShift = 3,
-    /// Alt modifier key is pressed
-    Alt = 4,
-    /// Control and alt modifier keys are pressed
-    CtrlAlt = 5,
-    /// Shift and alt modifier keys are pressed
-    ShiftAlt = 6,
-    /// Control, shift and alt modifier keys are pressed
-    CtrlShiftAlt = 7,
-    /// GUI modifier key is pressed
-    Gui = 8,
-    /// Control and Gui modifier keys are pressed
-    CtrlGui = 9,
-    /// Shift and Gui modifier keys are pressed
-    ShiftGui = 10,
-    /// Control, Shift and Gui modifier keys are pressed
-    CtrlShiftGui = 11,
-    /// Alt and Gui modifier keys are pressed
-    AltGui = 12,
-    /// Control, alt and gui modifier keys are pressed
-    CtrlAltGui = 13,
-    /// Shift, alt and gui modifier keys are pressed
-    ShiftAltGui = 14,
-    /// Control, shift, alt and gui modifier keys are pressed
-    CtrlShiftAltGui = 15,
-    RightAlt = 64,
+    pub const NONE: Modifier = Modifier(0);
+    pub const LEFT_CTRL: Modifier = Modifier(1 << 0);
+    pub const LEFT_SHIFT: Modifier = Modifier(1 << 1);
+    pub const LEFT_ALT: Modifier = Modifier(1 << 2);
+    pub const LEFT_GUI: Modifier = Modifier(1 << 3);
+    pub const RIGHT_CTRL: Modifier = Modifier(1 << 4);
+    pub const RIGHT_SHIFT: Modifier = Modifier(1 << 5);
+    /// AltGr on most layouts
+    pub const RIGHT_ALT: Modifier = Modifier(1 << 6);
+    pub const RIGHT_GUI: Modifier = Modifier(1 << 7);
+
+    /// The bits as they are in the first byte of a HID report.
+    pub fn bits(self) -> u8
+    {
+        self.0
+    }
+
+    pub fn is_empty(self) -> bool
+    {
+        self.0 == 0
+    }
+
+    /// Check if every key of `other` is pressed.
+    pub fn contains(self, other: Modifier) -> bool
+    {
+        self.0 & other.0 == other.0
+    }
+
+    /// Check if any key of `other` is pressed.
+    pub fn intersects(self, other: Modifier) -> bool
+    {
+        self.0 & other.0 != 0
+    }
+
+    /// Check if Ctrl is pressed on either side.
+    pub fn ctrl(self) -> bool
+    {
+        self.intersects(Modifier::LEFT_CTRL | Modifier::RIGHT_CTRL)
+    }
+
+    /// The same modifiers with the right hand keys moved onto the left ones, how the old enum saw them.
+    pub fn to_left(self) -> Modifier
+    {
+        Modifier((self.0 >> 4) | (self.0 & 0x0F))
+    }
+
+    /// The name of a combination in a layout file, if it has one.
+    fn name(self) -> Option<&'static str>
+    {
+        MODIFIER_NAMES.iter().find(|(bits, _)| *bits == self.0).map(|(_, name)| *name)
+    }
+
+    fn from_name(name: &str) -> Option<Modifier>
+    {
+        MODIFIER_NAMES.iter()
+            .chain(KEY_NAMES.iter())
+            .find(|(_, known)| *known == name)
+            .map(|(bits, _)| Modifier(*bits))
+    }
 }
 
+/// The names of the old modifier enum.
+const MODIFIER_NAMES: [(u8, &str); 17] =
+[
+    (0, "NoModifier"),
+    (1, "Ctrl"),
+    (2, "Shift"),
+    (3, "CtrlShift"),
+    (4, "Alt"),
+    (5, "CtrlAlt"),
+    (6, "ShiftAlt"),
+    (7, "CtrlShiftAlt"),
+    (8, "Gui"),
+    (9, "CtrlGui"),
+    (10, "ShiftGui"),
+    (11, "CtrlShiftGui"),
+    (12, "AltGui"),
+    (13, "CtrlAltGui"),
+    (14, "ShiftAltGui"),
+    (15, "CtrlShiftAltGui"),
+    (64, "RightAlt"),
+];
+
+/// The names of the single modifier keys, in the order of their bits.
+const KEY_NAMES: [(u8, &str); 8] =
+[
+    (1 << 0, "LeftCtrl"),
+    (1 << 1, "LeftShift"),
+    (1 << 2, "LeftAlt"),
+    (1 << 3, "LeftGui"),
+    (1 << 4, "RightCtrl"),
+    (1 << 5, "RightShift"),
+    (1 << 6, "RightAlt"),
+    (1 << 7, "RightGui"),
+];
+
 impl From<u8> for Modifier
 {
-    /// Will convert a u8 to a Modifier using a byte operation to check which bits are set
+    /// Every bit of the HID modifier byte is kept.
     fn from(num: u8) -> Modifier
     {
-        if num == 64 { return Modifier::RightAlt }
-        // Left modifier keys use the lower half of the u8
-        // Right modifier keys use the upper half of the u8
-        // Here we combine them into one Modifier.
-        match (num >> 4) | (num & 15)
+        Modifier(num)
+    }
+}
+
+impl std::ops::BitOr for Modifier
+{
+    type Output = Modifier;
+
+    fn bitor(self, other: Modifier) -> Modifier
+    {
+        Modifier(self.0 | other.0)
+    }
+}
+
+impl std::ops::BitOrAssign for Modifier
+{
+    fn bitor_assign(&mut self, other: Modifier)
+    {
+        self.0 |= other.0;
+    }
+}
+
+impl std::ops::BitAnd for Modifier
+{
+    type Output = Modifier;
+
+    fn bitand(self, other: Modifier) -> Modifier
+    {
+        Modifier(self.0 & other.0)
+    }
+}
+
+impl std::ops::Not for Modifier
+{
+    type Output = Modifier;
+
+    fn not(self) -> Modifier
+    {
+        Modifier(!self.0)
+    }
+}
+
+impl std::fmt::Debug for Modifier
+{
+    /// Lists the pressed keys, like `LeftShift | RightAlt`.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        if self.is_empty()
         {
-            0 => Modifier::NoModifier,
-            1 => Modifier::Ctrl,
-            2 => Modifier::Shift,
-            3 => Modifier::CtrlShift,
-            4 => Modifier::Alt,
-            5 => Modifier::CtrlAlt,
-            6 => Modifier::ShiftAlt,
-            7 => Modifier::CtrlShiftAlt,
-            8 => Modifier::Gui,
-            9 => Modifier::CtrlGui,
-            10 => Modifier::ShiftGui,
-            11 => Modifier::CtrlShiftGui,
-            12 => Modifier::AltGui,
-            13 => Modifier::CtrlAltGui,
-            14 => Modifier::ShiftAltGui,
-            15 => Modifier::CtrlShiftAltGui,
-            _ => Modifier::NoModifier,
+            return write!(f, "NoModifier")
         }
+        let names: Vec<&str> = KEY_NAMES.iter().filter(|(bits, _)| self.0 & bits != 0).map(|(_, name)| *name).collect();
+        write!(f, "{}", names.join(" | "))
+    }
+}
+
+impl Serialize for Modifier
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        use serde::ser::SerializeTupleVariant;
+        if let Some(name) = self.name()
+        {
+            return serializer.serialize_unit_variant("Modifier", self.0 as u32, name)
+        }
+        let keys: Vec<KeyName> = KEY_NAMES.iter().filter(|(bits, _)| self.0 & bits != 0).map(KeyName::new).collect();
+        let mut variant = serializer.serialize_tuple_variant("Modifier", 256, "Keys", keys.len())?;
+        for key in keys
+        {
+            variant.serialize_field(&key)?;
+        }
+        variant.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Modifier
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Modifier, D::Error>
+    {
+        deserializer.deserialize_enum("Modifier", &[], ModifierVisitor)
+    }
+}
+
+struct ModifierVisitor;
+
+impl<'de> serde::de::Visitor<'de> for ModifierVisitor
+{
+    type Value = Modifier;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(f, "a modifier name like Shift, or Keys with a list of modifier keys")
+    }
+
+    fn visit_enum<A: serde::de::EnumAccess<'de>>(self, data: A) -> Result<Modifier, A::Error>
+    {
+        use serde::de::{Error, VariantAccess};
+        let (Identifier(name), variant) = data.variant()?;
+        if name == "Keys"
+        {
+            return variant.tuple_variant(KEY_NAMES.len(), KeysVisitor)
+        }
+        variant.unit_variant()?;
+        Modifier::from_name(&name).ok_or_else(|| A::Error::custom(format!("unknown modifier {}", name)))
+    }
+}
+
+struct KeysVisitor;
+
+impl<'de> serde::de::Visitor<'de> for KeysVisitor
+{
+    type Value = Modifier;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(f, "a list of modifier keys like LeftShift")
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Modifier, A::Error>
+    {
+        let mut modifier = Modifier::NONE;
+        while let Some(key) = seq.next_element::<KeyName>()?
+        {
+            modifier |= Modifier(key.0);
+        }
+        Ok(modifier)
+    }
+}
+
+/// The name of an enum variant, read the way the format reads identifiers.
+struct Identifier(String);
+
+impl<'de> Deserialize<'de> for Identifier
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Identifier, D::Error>
+    {
+        deserializer.deserialize_identifier(IdentifierVisitor)
+    }
+}
+
+struct IdentifierVisitor;
+
+impl<'de> serde::de::Visitor<'de> for IdentifierVisitor
+{
+    type Value = Identifier;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(f, "a name")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<Identifier, E>
+    {
+        Ok(Identifier(name.to_owned()))
+    }
+}
+
+/// A single modifier key inside `Keys(..)`.
+struct KeyName(u8, &'static str);
+
+impl KeyName
+{
+    fn new(&(bits, name): &(u8, &'static str)) -> KeyName
+    {
+        KeyName(bits, name)
+    }
+}
+
+impl Serialize for KeyName
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        serializer.serialize_unit_variant("ModifierKey", self.0.trailing_zeros(), self.1)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyName
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<KeyName, D::Error>
+    {
+        deserializer.deserialize_enum("ModifierKey", &[], KeyNameVisitor)
+    }
+}
+
+struct KeyNameVisitor;
+
+impl<'de> serde::de::Visitor<'de> for KeyNameVisitor
+{
+    type Value = KeyName;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(f, "a modifier key like LeftShift")
+    }
+
+    fn visit_enum<A: serde::de::EnumAccess<'de>>(self, data: A) -> Result<KeyName, A::Error>
+    {
+        use serde::de::{Error, VariantAccess};
+        let (Identifier(name), variant) = data.variant()?;
+        variant.unit_variant()?;
+        KEY_NAMES.iter()
+            .find(|(_, known)| *known == name)
+            .map(KeyName::new)
+            .ok_or_else(|| A::Error::custom(format!("unknown modifier key {}", name)))
     }
 }
 
 /// Keyboard input values.
 ///
 /// Type Keypress represents the different type of inputs from the keyboard
+///
+/// Layout files refer to the named keys by their variant, typed sequences by their name in braces, like `Escape`, `F(13)` or `Keypad('5')`.
 #[non_exhaustive]
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Deserialize, Serialize)]
 pub enum Key
@@ -322,7 +597,7 @@ pub enum Key
     Char(char),
     // The enter key
     Enter,
-    // The F keys
+    // The F keys, F1 to F24
     F(u8),       
     // A combination of a modifier key and regular input defined by user
     Macro, 
@@ -330,6 +605,194 @@ pub enum Key
     Backspace,
     // A key or combination of keys that do not fall in the other Keypress values
     Undefined(Modifier, u8),
+    Escape,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Up,
+    Down,
+    Left,
+    Right,
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    PrintScreen,
+    Pause,
+    /// The digits, `/`, `*`, `-`, `+` and `.` on the keypad
+    Keypad(char),
+    /// The enter key on the keypad
+    KeypadEnter,
+}
+
+/// The keys that are the same on every layout, with their HID usage.
+const NAMED_KEYS: [(Key, u8); 32] =
+[
+    (Key::Escape, 0x29),
+    (Key::CapsLock, 0x39),
+    (Key::PrintScreen, 0x46),
+    (Key::ScrollLock, 0x47),
+    (Key::Pause, 0x48),
+    (Key::Insert, 0x49),
+    (Key::Home, 0x4a),
+    (Key::PageUp, 0x4b),
+    (Key::Delete, 0x4c),
+    (Key::End, 0x4d),
+    (Key::PageDown, 0x4e),
+    (Key::Right, 0x4f),
+    (Key::Left, 0x50),
+    (Key::Down, 0x51),
+    (Key::Up, 0x52),
+    (Key::NumLock, 0x53),
+    (Key::Keypad('/'), 0x54),
+    (Key::Keypad('*'), 0x55),
+    (Key::Keypad('-'), 0x56),
+    (Key::Keypad('+'), 0x57),
+    (Key::KeypadEnter, 0x58),
+    (Key::Keypad('1'), 0x59),
+    (Key::Keypad('2'), 0x5a),
+    (Key::Keypad('3'), 0x5b),
+    (Key::Keypad('4'), 0x5c),
+    (Key::Keypad('5'), 0x5d),
+    (Key::Keypad('6'), 0x5e),
+    (Key::Keypad('7'), 0x5f),
+    (Key::Keypad('8'), 0x60),
+    (Key::Keypad('9'), 0x61),
+    (Key::Keypad('0'), 0x62),
+    (Key::Keypad('.'), 0x63),
+];
+
+impl Key
+{
+    /// The named keys and F keys with their default HID usage, these do not depend on the layout.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::converter::Key;
+    /// assert!(Key::named_keys().any(|pair| pair == (Key::F(24), 0x73)));
+    /// ```
+    pub fn named_keys() -> impl Iterator<Item = (Key, u8)>
+    {
+        let f_keys = (1..=12).map(|n| (Key::F(n), 0x39 + n)).chain((13..=24).map(|n| (Key::F(n), 0x5b + n)));
+        NAMED_KEYS.iter().copied().chain(f_keys)
+    }
+
+    /// Read the keys of a text typed by a macro, a name in braces like `{Tab}`, `{Enter}` or `{F13}` is that key.
+    ///
+    /// Braces around anything that is not the name of a key, see [`Key::from_str`](#impl-FromStr-for-Key), are typed as they are.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::converter::Key;
+    /// let keys: Vec<Key> = Key::sequence("a{Tab}{b}{Up}").collect();
+    /// assert_eq!(vec![Key::Char('a'), Key::Char('\t'), Key::Char('{'), Key::Char('b'), Key::Char('}'), Key::Up], keys);
+    /// ```
+    pub fn sequence(text: &str) -> KeySequence<'_>
+    {
+        KeySequence { rest: text }
+    }
+}
+
+impl std::str::FromStr for Key
+{
+    type Err = Error;
+
+    /// Read a key the way it is written in a sequence: a single character, or a name like `Escape`, `F13`, `Keypad5` or `KeypadEnter`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::converter::Key;
+    /// assert_eq!(Key::PageDown, "PageDown".parse().unwrap());
+    /// assert_eq!(Key::F(13), "F13".parse().unwrap());
+    /// assert_eq!(Key::Keypad('+'), "Keypad+".parse().unwrap());
+    /// assert_eq!(Key::Char('x'), "x".parse().unwrap());
+    /// ```
+    fn from_str(name: &str) -> Result<Key, Error>
+    {
+        let mut chars = name.chars();
+        let key = match (chars.next(), chars.next()) 
+        {
+            (Some(c), None) => Some(Key::Char(c)),
+            _ => match name
+            {
+                "Enter" => Some(Key::Enter),
+                "Backspace" => Some(Key::Backspace),
+                "Tab" => Some(Key::Char('\t')),
+                "Space" => Some(Key::Char(' ')),
+                "Macro" => Some(Key::Macro),
+                "Escape" => Some(Key::Escape),
+                "Insert" => Some(Key::Insert),
+                "Delete" => Some(Key::Delete),
+                "Home" => Some(Key::Home),
+                "End" => Some(Key::End),
+                "PageUp" => Some(Key::PageUp),
+                "PageDown" => Some(Key::PageDown),
+                "Up" => Some(Key::Up),
+                "Down" => Some(Key::Down),
+                "Left" => Some(Key::Left),
+                "Right" => Some(Key::Right),
+                "CapsLock" => Some(Key::CapsLock),
+                "NumLock" => Some(Key::NumLock),
+                "ScrollLock" => Some(Key::ScrollLock),
+                "PrintScreen" => Some(Key::PrintScreen),
+                "Pause" => Some(Key::Pause),
+                "KeypadEnter" => Some(Key::KeypadEnter),
+                _ if name.starts_with("Keypad") => 
+                {
+                    let mut rest = name["Keypad".len()..].chars();
+                    match (rest.next(), rest.next())
+                    {
+                        (Some(c), None) if c.is_ascii_digit() || "/*-+.".contains(c) => Some(Key::Keypad(c)),
+                        _ => None,
+                    }
+                },
+                _ if name.starts_with('F') => match name[1..].parse()
+                {
+                    Ok(n) if (1..=24).contains(&n) => Some(Key::F(n)),
+                    _ => None,
+                },
+                _ => None,
+            },
+        };
+        key.ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("unknown key {}", name)))
+    }
+}
+
+/// The keys of a typed sequence, see [`Key::sequence`].
+#[derive(Debug, Clone)]
+pub struct KeySequence<'a>
+{
+    rest: &'a str,
+}
+
+impl<'a> Iterator for KeySequence<'a>
+{
+    type Item = Key;
+
+    fn next(&mut self) -> Option<Key>
+    {
+        let c = self.rest.chars().next()?;
+        if c == '{'
+        {
+            // Only names of two or more characters are keys, so `{a}` in a password is still typed as it is
+            if let Some(end) = self.rest.find('}')
+            {
+                let name = &self.rest[1..end];
+                if name.chars().count() > 1
+                {
+                    if let Ok(key) = name.parse()
+                    {
+                        self.rest = &self.rest[end + 1..];
+                        return Some(key)
+                    }
+                }
+            }
+        }
+        self.rest = &self.rest[c.len_utf8()..];
+        Some(Key::Char(c))
+    }
 }
 
 #[cfg(test)]
@@ -346,13 +809,13 @@ mod tests
 
         let keypress = Key::Char('a');
 
-        assert_eq!((Modifier::NoModifier, 0x0u8), converter.convert_keypress(&keypress));
+        assert_eq!((Modifier::NONE, 0x0u8), converter.get_raw(&keypress));
 
-        let modifier = Modifier::Ctrl;
+        let modifier = Modifier::LEFT_CTRL;
 
         let raw_key_code = 0x04u8; // The a key as defined by the HID USB usages and descriptions
 
-        assert_eq!(Key::Undefined(Modifier::Ctrl, 0x04u8), converter.convert_rawinput(&modifier, &raw_key_code));
+        assert_eq!(Key::Undefined(Modifier::LEFT_CTRL, 0x04u8), converter.get_key(&(modifier, raw_key_code)));
     }
     
     #[test]
@@ -360,15 +823,15 @@ mod tests
     {
         let mut converter = Converter::new();
 
-        assert!(!converter.input_map.contains_key(&Key::Macro));
+        assert!(!converter.output_map.contains_key(&Key::Macro));
 
-        let modifier = Modifier::Ctrl;
+        let modifier = Modifier::LEFT_CTRL;
 
         let raw_key_code = 0x04u8;
 
         converter.add_macro(modifier, raw_key_code);
 
-        assert!(converter.input_map.contains_key(&Key::Macro));
+        assert!(converter.output_map.contains_key(&Key::Macro));
     }
 
     #[test]
@@ -376,15 +839,15 @@ mod tests
     {
         let mut converter = Converter::new();
 
-        assert!(!converter.input_map.contains_key(&Key::Char('a')));
+        assert!(!converter.output_map.contains_key(&Key::Char('a')));
 
-        let modifier = Modifier::NoModifier;
+        let modifier = Modifier::NONE;
 
         let raw_key_code = 0x04u8;
 
-        converter.add_character('a', modifier, raw_key_code);
+        converter.add_type(Key::Char('a'), modifier, raw_key_code);
 
-        assert!(converter.input_map.contains_key(&Key::Char('a')));
+        assert!(converter.output_map.contains_key(&Key::Char('a')));
     }
 
     #[test]
@@ -394,12 +857,12 @@ mod tests
 
         let keypress = Key::Char('a');
 
-        assert_eq!((Modifier::NoModifier, 0x04u8), converter.convert_keypress(&keypress));
+        assert_eq!((Modifier::NONE, 0x04u8), converter.get_raw(&keypress));
 
         // unknown characters should return 0
         let keypress = Key::Char('b');
 
-        assert_eq!((Modifier::NoModifier, 0x05u8), converter.convert_keypress(&keypress));
+        assert_eq!((Modifier::NONE, 0x05u8), converter.get_raw(&keypress));
     }
 
     #[test]
@@ -409,12 +872,12 @@ mod tests
 
         let keypress = Key::Char('A');
 
-        assert_eq!((Modifier::Shift, 0x04u8), converter.convert_keypress(&keypress));
+        assert_eq!((Modifier::LEFT_SHIFT, 0x04u8), converter.get_raw(&keypress));
 
         // unknown characters should return 0
         let keypress = Key::Char('B');
 
-        assert_eq!((Modifier::Shift, 0x05u8), converter.convert_keypress(&keypress));
+        assert_eq!((Modifier::LEFT_SHIFT, 0x05u8), converter.get_raw(&keypress));
     }
 
     #[test]
@@ -425,49 +888,160 @@ mod tests
         // unknown characters should return 0
         let keypress = Key::Char('💖');
 
-        assert_eq!((Modifier::NoModifier, 0u8), converter.convert_keypress(&keypress));
+        assert_eq!((Modifier::NONE, 0u8), converter.get_raw(&keypress));
     }
 
     #[test]
     fn test_convert_rawinput_lowercase_character()
     {
         let converter = Converter::default();
-        let modifier_key = Modifier::NoModifier;
+        let modifier_key = Modifier::NONE;
         let raw_key_code_a = 0x04u8; // The a key on a qwerty keyboard
 
-        assert_eq!(Key::Char('a'), converter.convert_rawinput(&modifier_key, &raw_key_code_a));
+        assert_eq!(Key::Char('a'), converter.get_key(&(modifier_key, raw_key_code_a)));
 
         let raw_key_code_b = 0x05u8;
         
-        assert_eq!(Key::Char('b'), converter.convert_rawinput(&modifier_key, &raw_key_code_b));
+        assert_eq!(Key::Char('b'), converter.get_key(&(modifier_key, raw_key_code_b)));
     }
 
     #[test]
     fn test_convert_rawinput_uppercase_character()
     {
         let converter = Converter::default();
-        let modifier_key = Modifier::Shift;
+        let modifier_key = Modifier::LEFT_SHIFT;
         let raw_key_code_a = 0x04u8; // The a key on a qwerty keyboard
 
-        assert_eq!(Key::Char('A'), converter.convert_rawinput(&modifier_key, &raw_key_code_a));
+        assert_eq!(Key::Char('A'), converter.get_key(&(modifier_key, raw_key_code_a)));
 
         let raw_key_code_b = 0x05u8;
         
-        assert_eq!(Key::Char('B'), converter.convert_rawinput(&modifier_key, &raw_key_code_b));
+        assert_eq!(Key::Char('B'), converter.get_key(&(modifier_key, raw_key_code_b)));
     }
 
     #[test]
     fn test_convert_rawinput_unknown_character()
     {
         let converter = Converter::default();
-        let modifier_key = Modifier::NoModifier;
+        let modifier_key = Modifier::NONE;
         let raw_key_code = 0x01u8;
 
-        assert_eq!(Key::Undefined(Modifier::NoModifier, 0x01u8), converter.convert_rawinput(&modifier_key, &raw_key_code));
+        assert_eq!(Key::Undefined(Modifier::NONE, 0x01u8), converter.get_key(&(modifier_key, raw_key_code)));
 
-        let modifier_key = Modifier::Ctrl;
+        let modifier_key = Modifier::LEFT_CTRL;
         let raw_key_code = 0;
 
-        assert_eq!(Key::Undefined(Modifier::Ctrl, 0), converter.convert_rawinput(&modifier_key, &raw_key_code));
+        assert_eq!(Key::Undefined(Modifier::LEFT_CTRL, 0), converter.get_key(&(modifier_key, raw_key_code)));
+    }
+}
+
+#[cfg(test)]
+mod layouttests
+{
+    use super::*;
+
+    #[test]
+    fn test_modifier_keeps_every_bit()
+    {
+        for bits in 0..=255u8
+        {
+            assert_eq!(bits, Modifier::from(bits).bits());
+        }
+        let altgr_shift = Modifier::RIGHT_ALT | Modifier::LEFT_SHIFT;
+        assert_ne!(Modifier::LEFT_ALT | Modifier::LEFT_SHIFT, altgr_shift);
+        assert!(altgr_shift.contains(Modifier::RIGHT_ALT));
+        assert!(!altgr_shift.ctrl());
+        assert!(Modifier::RIGHT_CTRL.ctrl());
+        assert_eq!(Modifier::LEFT_ALT | Modifier::LEFT_SHIFT, altgr_shift.to_left());
+    }
+
+    #[test]
+    fn test_legacy_layout_names_load()
+    {
+        let pairs: Vec<(Key, (Modifier, u8))> = ron::from_str("[(Char('a'), (NoModifier, 4)), (Char('A'), (Shift, 4)), (Macro, (CtrlShiftAltGui, 9)), (Char('@'), (RightAlt, 20))]").unwrap();
+        assert_eq!(Modifier::NONE, (pairs[0].1).0);
+        assert_eq!(Modifier::LEFT_SHIFT, (pairs[1].1).0);
+        assert_eq!(Modifier::from(15), (pairs[2].1).0);
+        assert_eq!(Modifier::RIGHT_ALT, (pairs[3].1).0);
+    }
+
+    #[test]
+    fn test_modifier_round_trip()
+    {
+        for bits in 0..=255u8
+        {
+            let modifier = Modifier::from(bits);
+            let text = ron::to_string(&modifier).unwrap();
+            assert_eq!(modifier, ron::from_str::<Modifier>(&text).unwrap(), "{}", text);
+        }
+        assert_eq!("CtrlShift", ron::to_string(&Modifier::from(3)).unwrap());
+        assert_eq!("Keys(LeftShift,RightAlt)", ron::to_string(&(Modifier::LEFT_SHIFT | Modifier::RIGHT_ALT)).unwrap());
+        assert!(ron::from_str::<Modifier>("Hyper").is_err());
+    }
+
+    #[test]
+    fn test_named_keys_have_default_codes()
+    {
+        let converter = Converter::default();
+        assert_eq!(Key::Escape, converter.get_key(&(Modifier::NONE, 0x29)));
+        assert_eq!(Key::Keypad('0'), converter.get_key(&(Modifier::NONE, 0x62)));
+        assert_eq!((Modifier::NONE, 0x3a), converter.get_raw(&Key::F(1)));
+        assert_eq!((Modifier::NONE, 0x45), converter.get_raw(&Key::F(12)));
+        assert_eq!((Modifier::NONE, 0x68), converter.get_raw(&Key::F(13)));
+        assert_eq!((Modifier::NONE, 0x73), converter.get_raw(&Key::F(24)));
+        assert_eq!(24 + NAMED_KEYS.len(), Key::named_keys().count());
+    }
+
+    #[test]
+    fn test_key_from_name()
+    {
+        assert_eq!(Key::Escape, "Escape".parse::<Key>().unwrap());
+        assert_eq!(Key::F(24), "F24".parse::<Key>().unwrap());
+        assert_eq!(Key::Char('F'), "F".parse::<Key>().unwrap());
+        assert_eq!(Key::Keypad('.'), "Keypad.".parse::<Key>().unwrap());
+        assert_eq!(Key::Char('\t'), "Tab".parse::<Key>().unwrap());
+        assert!("F25".parse::<Key>().is_err());
+        assert!("Keypadx".parse::<Key>().is_err());
+        assert!("Hyper".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn test_sequence()
+    {
+        let keys: Vec<Key> = Key::sequence("user{Tab}pass{Enter}").collect();
+        let mut expected: Vec<Key> = "user".chars().map(Key::Char).collect();
+        expected.push(Key::Char('\t'));
+        expected.extend("pass".chars().map(Key::Char));
+        expected.push(Key::Enter);
+        assert_eq!(expected, keys);
+
+        assert_eq!(vec![Key::Home, Key::F(13), Key::Keypad('5'), Key::Char('x')], Key::sequence("{Home}{F13}{Keypad5}x").collect::<Vec<_>>());
+        // Anything that is not a key name stays as it was typed
+        for text in ["{}", "{a}", "{Hyper}", "a{b", "}{"].iter()
+        {
+            assert_eq!(text.chars().map(Key::Char).collect::<Vec<_>>(), Key::sequence(text).collect::<Vec<_>>());
+        }
+        assert_eq!(vec![Key::Char('{'), Key::Up], Key::sequence("{{Up}").collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_named_keys_in_layout_files()
+    {
+        let pairs: Vec<(Key, (Modifier, u8))> = ron::from_str("[(Escape, (NoModifier, 41)), (F(13), (NoModifier, 104)), (Keypad('5'), (NoModifier, 93))]").unwrap();
+        assert_eq!(Key::Escape, pairs[0].0);
+        assert_eq!(Key::F(13), pairs[1].0);
+        assert_eq!(Key::Keypad('5'), pairs[2].0);
+    }
+
+    #[test]
+    fn test_right_modifiers_fall_back_to_left()
+    {
+        let mut converter = Converter::default();
+        assert_eq!(Key::Char('A'), converter.get_key(&(Modifier::RIGHT_SHIFT, 0x04)));
+
+        converter.add_type(Key::Char('@'), Modifier::RIGHT_ALT, 0x14);
+        assert_eq!(Key::Char('@'), converter.get_key(&(Modifier::RIGHT_ALT, 0x14)));
+        assert_eq!(Key::Undefined(Modifier::LEFT_ALT, 0x14), converter.get_key(&(Modifier::LEFT_ALT, 0x14)));
+        assert_eq!((Modifier::RIGHT_ALT, 0x14), converter.get_raw(&Key::Char('@')));
     }
 }
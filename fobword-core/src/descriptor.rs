@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+
+use crate::error::DataHandleError;
+
+const USAGE_PAGE_GENERIC_DESKTOP: u32 = 0x01;
+const USAGE_PAGE_KEYBOARD: u32 = 0x07;
+const USAGE_KEYBOARD: u32 = 0x06;
+const USAGE_KEYPAD: u32 = 0x07;
+const COLLECTION_APPLICATION: u32 = 0x01;
+/// Left control, the first of the eight modifier usages.
+const FIRST_MODIFIER: u32 = 0xe0;
+const LAST_MODIFIER: u32 = 0xe7;
+/// The largest report read from a keyboard in bytes, with its report id. Descriptors with larger input reports are refused.
+pub const MAXIMUM_REPORT_SIZE: usize = 64;
+/// Usages below this are the error codes a keyboard sends instead of keys, like rollover errors.
+const FIRST_KEY: u32 = 0x04;
+
+/// Where the keys are in the input reports of one keyboard.
+///
+/// A device can have more than one, every keyboard collection or report id gets its own.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ReportFormat
+{
+    /// The first byte of the report, when the device numbers its reports
+    pub report_id: Option<u8>,
+    /// Length of the report in bytes, without the report id
+    pub length: usize,
+    /// The bit in the report of every modifier, from left control to right gui
+    pub modifiers: [Option<usize>; 8],
+    /// Lists of pressed key codes, the boot report has one with six entries
+    pub arrays: Vec<KeyArray>,
+    /// One bit per key, used by keyboards with n-key rollover
+    pub bitmaps: Vec<KeyBitmap>,
+}
+
+/// A list of key codes in a report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyArray
+{
+    /// Bit of the first entry
+    pub offset: usize,
+    /// Bits per entry
+    pub size: usize,
+    pub count: usize,
+    /// The value of the usage at `usage_minimum`
+    pub logical_minimum: i32,
+    pub usage_minimum: u32,
+}
+
+/// A run of bits in a report, one per key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBitmap
+{
+    /// Bit of the first key
+    pub offset: usize,
+    /// The usage of the first key
+    pub usage_minimum: u32,
+    pub count: usize,
+}
+
+impl ReportFormat
+{
+    /// Check if a report read from the device has this format.
+    pub fn matches(&self, report: &[u8]) -> bool
+    {
+        match self.report_id
+        {
+            Some(id) => report.first() == Some(&id),
+            None => true,
+        }
+    }
+
+    /// The modifier byte and the pressed keys of a report, `None` if the report has another format.
+    ///
+    /// Modifiers sent as key codes are moved to the modifier byte.
+    pub fn decode(&self, report: &[u8]) -> Option<(u8, Vec<u8>)>
+    {
+        if !self.matches(report)
+        {
+            return None
+        }
+        let data = if self.report_id.is_some() { &report[1..] } else { report };
+
+        let mut modifiers = 0u8;
+        for (index, bit) in self.modifiers.iter().enumerate()
+        {
+            if let Some(bit) = bit
+            {
+                if read_bits(data, *bit, 1) != 0
+                {
+                    modifiers |= 1 << index;
+                }
+            }
+        }
+
+        let mut keys = Vec::new();
+        let mut add_key = |usage: u32|
+        {
+            match usage
+            {
+                FIRST_MODIFIER..=LAST_MODIFIER => modifiers |= 1 << (usage - FIRST_MODIFIER),
+                FIRST_KEY..=0xff if !keys.contains(&(usage as u8)) => keys.push(usage as u8),
+                _ => (),
+            }
+        };
+        for array in self.arrays.iter()
+        {
+            for index in 0..array.count
+            {
+                let value = read_bits(data, array.offset + index * array.size, array.size) as i64;
+                let usage = array.usage_minimum as i64 + value - array.logical_minimum as i64;
+                if value != 0 && usage >= 0
+                {
+                    add_key(usage as u32);
+                }
+            }
+        }
+        for bitmap in self.bitmaps.iter()
+        {
+            for index in 0..bitmap.count
+            {
+                if read_bits(data, bitmap.offset + index, 1) != 0
+                {
+                    add_key(bitmap.usage_minimum + index as u32);
+                }
+            }
+        }
+        Some((modifiers, keys))
+    }
+
+    /// A report as an 8 byte boot report, with the first six keys when more are pressed.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::descriptor;
+    /// # const BOOT_KEYBOARD: &[u8] = &[
+    /// #     0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7, 0x15, 0x00, 0x25, 0x01,
+    /// #     0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01, 0x75, 0x08, 0x81, 0x01, 0x95, 0x05, 0x75, 0x01,
+    /// #     0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02, 0x95, 0x01, 0x75, 0x03, 0x91, 0x01, 0x95, 0x06,
+    /// #     0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x81, 0x00, 0xc0,
+    /// # ];
+    /// let formats = descriptor::parse(BOOT_KEYBOARD).unwrap();
+    /// assert_eq!(Some([0x02, 0, 0x04, 0, 0, 0, 0, 0]), formats[0].boot_report(&[0x02, 0, 0x04, 0, 0, 0, 0, 0]));
+    /// ```
+    pub fn boot_report(&self, report: &[u8]) -> Option<[u8; 8]>
+    {
+        let (modifiers, keys) = self.decode(report)?;
+        let mut boot = [0u8; 8];
+        boot[0] = modifiers;
+        for (slot, key) in boot[2..].iter_mut().zip(keys.iter())
+        {
+            *slot = *key;
+        }
+        Some(boot)
+    }
+}
+
+/// Read up to 32 bits starting at a bit position, little endian like everything in HID.
+fn read_bits(data: &[u8], offset: usize, size: usize) -> u32
+{
+    let mut value = 0u32;
+    for bit in 0..size.min(32)
+    {
+        let position = offset + bit;
+        if let Some(byte) = data.get(position / 8)
+        {
+            value |= (((byte >> (position % 8)) & 1) as u32) << bit;
+        }
+    }
+    value
+}
+
+/// The global items, these stay until they are changed.
+#[derive(Debug, Clone, Copy, Default)]
+struct Globals
+{
+    usage_page: u32,
+    logical_minimum: i32,
+    report_size: usize,
+    report_count: usize,
+    report_id: Option<u8>,
+}
+
+/// The local items, these are cleared after every main item.
+#[derive(Debug, Default)]
+struct Locals
+{
+    /// Usages with the page in the high 16 bits when the item said so, 0 there otherwise
+    usages: Vec<u32>,
+    usage_minimum: Option<u32>,
+    usage_maximum: Option<u32>,
+}
+
+impl Locals
+{
+    /// The usage of the n-th field of a main item, with the usage page filled in.
+    fn usage(&self, index: usize, usage_page: u32) -> Option<u32>
+    {
+        let usage = match (self.usages.get(index).or_else(|| self.usages.last()), self.usage_minimum)
+        {
+            (Some(usage), _) => *usage,
+            (None, Some(minimum)) => (minimum + index as u32).min(self.usage_maximum.unwrap_or(u32::MAX)),
+            (None, None) => return None,
+        };
+        Some(if usage > 0xffff { usage } else { usage_page << 16 | usage })
+    }
+}
+
+/// Find the keyboards in a HID report descriptor and where their keys are in the input reports.
+///
+/// Returns an empty list for devices without a keyboard or keypad application collection,
+/// and `InvalidDescriptor` when the descriptor can't be read or has input reports longer than [`MAXIMUM_REPORT_SIZE`].
+///
+/// # Examples
+/// ```
+/// # use fobword_core::descriptor;
+/// # const BOOT_KEYBOARD: &[u8] = &[
+/// #     0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7, 0x15, 0x00, 0x25, 0x01,
+/// #     0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01, 0x75, 0x08, 0x81, 0x01, 0x95, 0x05, 0x75, 0x01,
+/// #     0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02, 0x95, 0x01, 0x75, 0x03, 0x91, 0x01, 0x95, 0x06,
+/// #     0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x81, 0x00, 0xc0,
+/// # ];
+/// let formats = descriptor::parse(BOOT_KEYBOARD).unwrap();
+/// assert_eq!(1, formats.len());
+/// assert_eq!(8, formats[0].length);
+/// ```
+pub fn parse(descriptor: &[u8]) -> Result<Vec<ReportFormat>, DataHandleError>
+{
+    let mut globals = Globals::default();
+    let mut stack: Vec<Globals> = Vec::new();
+    let mut locals = Locals::default();
+    // Per collection if it is a keyboard application
+    let mut collections: Vec<bool> = Vec::new();
+    // The next free bit of every input report
+    let mut offsets: HashMap<Option<u8>, usize> = HashMap::new();
+    let mut formats: Vec<ReportFormat> = Vec::new();
+
+    let mut position = 0;
+    while position < descriptor.len()
+    {
+        let prefix = descriptor[position];
+        // Long items are reserved, nothing uses them but they have to be skipped
+        if prefix == 0xfe
+        {
+            let size = *descriptor.get(position + 1).ok_or_else(|| invalid("long item without a size", position))? as usize;
+            position += 3 + size;
+            continue;
+        }
+        let size = match prefix & 0x03 { 3 => 4, size => size as usize };
+        let bytes = descriptor.get(position + 1..position + 1 + size).ok_or_else(|| invalid("item runs past the end", position))?;
+        let data = bytes.iter().rev().fold(0u32, |data, byte| data << 8 | *byte as u32);
+        // Sign extend for the items that can be negative
+        let signed = match size
+        {
+            1 => data as u8 as i8 as i32,
+            2 => data as u16 as i16 as i32,
+            _ => data as i32,
+        };
+
+        match (prefix >> 2) & 0x03
+        {
+            // Main items
+            0 =>
+            {
+                match prefix >> 4
+                {
+                    // Input
+                    0x8 =>
+                    {
+                        let offset = offsets.entry(globals.report_id).or_insert(0);
+                        let start = *offset;
+                        // The report id takes the first byte of the report
+                        let limit = (MAXIMUM_REPORT_SIZE - globals.report_id.map_or(0, |_| 1)) * 8;
+                        *offset = globals.report_size.checked_mul(globals.report_count)
+                            .and_then(|bits| start.checked_add(bits))
+                            .filter(|end| *end <= limit)
+                            .ok_or_else(|| invalid("input report too long", position))?;
+                        let constant = data & 0x01 != 0;
+                        if collections.contains(&true) && !constant
+                        {
+                            let format = format_for(&mut formats, globals.report_id);
+                            add_fields(format, &globals, &locals, data & 0x02 != 0, start);
+                        }
+                    },
+                    // Collection
+                    0xa =>
+                    {
+                        let keyboard = data == COLLECTION_APPLICATION && matches!(locals.usage(0, globals.usage_page),
+                            Some(usage) if usage == USAGE_PAGE_GENERIC_DESKTOP << 16 | USAGE_KEYBOARD
+                                || usage == USAGE_PAGE_GENERIC_DESKTOP << 16 | USAGE_KEYPAD);
+                        collections.push(keyboard);
+                    },
+                    // End collection
+                    0xc =>
+                    {
+                        collections.pop().ok_or_else(|| invalid("end of a collection that was not started", position))?;
+                    },
+                    // Output, feature, these are not read
+                    _ => (),
+                }
+                locals = Locals::default();
+            },
+            // Global items
+            1 => match prefix >> 4
+            {
+                0x0 => globals.usage_page = data,
+                0x1 => globals.logical_minimum = signed,
+                0x7 => globals.report_size = data as usize,
+                0x8 => globals.report_id = Some(data as u8),
+                0x9 => globals.report_count = data as usize,
+                0xa => stack.push(globals),
+                0xb => globals = stack.pop().ok_or_else(|| invalid("pop without a push", position))?,
+                _ => (),
+            },
+            // Local items
+            2 => match prefix >> 4
+            {
+                0x0 => locals.usages.push(if size == 4 { data } else { data & 0xffff }),
+                0x1 => locals.usage_minimum = Some(data),
+                0x2 => locals.usage_maximum = Some(data),
+                _ => (),
+            },
+            _ => (),
+        }
+        position += 1 + size;
+    }
+
+    for format in formats.iter_mut()
+    {
+        format.length = offsets.get(&format.report_id).copied().unwrap_or(0).div_ceil(8);
+    }
+    formats.retain(|format| format.modifiers.iter().any(Option::is_some) || !format.arrays.is_empty() || !format.bitmaps.is_empty());
+    Ok(formats)
+}
+
+fn invalid(message: &str, position: usize) -> DataHandleError
+{
+    DataHandleError::InvalidDescriptor(format!("{} at byte {}", message, position))
+}
+
+fn format_for(formats: &mut Vec<ReportFormat>, report_id: Option<u8>) -> &mut ReportFormat
+{
+    match formats.iter().position(|format| format.report_id == report_id)
+    {
+        Some(index) => &mut formats[index],
+        None =>
+        {
+            formats.push(ReportFormat { report_id, ..ReportFormat::default() });
+            formats.last_mut().unwrap()
+        },
+    }
+}
+
+/// Add the keyboard fields of an input item to a format, fields of other usage pages are skipped.
+fn add_fields(format: &mut ReportFormat, globals: &Globals, locals: &Locals, variable: bool, start: usize)
+{
+    if !variable
+    {
+        // An array has one usage range for all its entries
+        if let Some(minimum) = locals.usage(0, globals.usage_page)
+        {
+            if minimum >> 16 == USAGE_PAGE_KEYBOARD
+            {
+                format.arrays.push(KeyArray
+                {
+                    offset: start,
+                    size: globals.report_size,
+                    count: globals.report_count,
+                    logical_minimum: globals.logical_minimum,
+                    usage_minimum: minimum & 0xffff,
+                });
+            }
+        }
+        return
+    }
+    for index in 0..globals.report_count
+    {
+        let usage = match locals.usage(index, globals.usage_page)
+        {
+            Some(usage) if usage >> 16 == USAGE_PAGE_KEYBOARD && globals.report_size == 1 => usage & 0xffff,
+            _ => continue,
+        };
+        let bit = start + index;
+        match usage
+        {
+            FIRST_MODIFIER..=LAST_MODIFIER => format.modifiers[(usage - FIRST_MODIFIER) as usize] = Some(bit),
+            _ => match format.bitmaps.last_mut()
+            {
+                Some(bitmap) if bitmap.offset + bitmap.count == bit && bitmap.usage_minimum + bitmap.count as u32 == usage => bitmap.count += 1,
+                _ => format.bitmaps.push(KeyBitmap { offset: bit, usage_minimum: usage, count: 1 }),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod descriptortests
+{
+    use super::*;
+
+    /// The boot keyboard from appendix B.1 of the HID specification.
+    const BOOT_KEYBOARD: &[u8] = &
+    [
+        0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7, 0x15, 0x00, 0x25, 0x01,
+        0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01, 0x75, 0x08, 0x81, 0x01, 0x95, 0x05, 0x75, 0x01,
+        0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02, 0x95, 0x01, 0x75, 0x03, 0x91, 0x01, 0x95, 0x06,
+        0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x81, 0x00, 0xc0,
+    ];
+
+    /// The boot mouse from appendix B.2 of the HID specification.
+    const BOOT_MOUSE: &[u8] = &
+    [
+        0x05, 0x01, 0x09, 0x02, 0xa1, 0x01, 0x09, 0x01, 0xa1, 0x00, 0x05, 0x09, 0x19, 0x01, 0x29, 0x03,
+        0x15, 0x00, 0x25, 0x01, 0x95, 0x03, 0x75, 0x01, 0x81, 0x02, 0x95, 0x01, 0x75, 0x05, 0x81, 0x01,
+        0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7f, 0x75, 0x08, 0x95, 0x02, 0x81, 0x06,
+        0xc0, 0xc0,
+    ];
+
+    /// A keyboard with media keys on one interface, like most office keyboards: report 1 is the keyboard,
+    /// report 2 the consumer control keys.
+    const COMPOSITE_KEYBOARD: &[u8] = &
+    [
+        0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x85, 0x01, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7, 0x15, 0x00,
+        0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01, 0x75, 0x08, 0x81, 0x03, 0x95, 0x05,
+        0x75, 0x01, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02, 0x95, 0x01, 0x75, 0x03, 0x91, 0x03,
+        0x95, 0x06, 0x75, 0x08, 0x15, 0x00, 0x26, 0xff, 0x00, 0x05, 0x07, 0x19, 0x00, 0x2a, 0xff, 0x00,
+        0x81, 0x00, 0xc0, 0x05, 0x0c, 0x09, 0x01, 0xa1, 0x01, 0x85, 0x02, 0x15, 0x00, 0x26, 0x3c, 0x02,
+        0x19, 0x00, 0x2a, 0x3c, 0x02, 0x75, 0x10, 0x95, 0x01, 0x81, 0x00, 0xc0,
+    ];
+
+    /// An n-key rollover keyboard like QMK builds them: report 6 has the modifiers and a bitmap of 120 keys.
+    const NKRO_KEYBOARD: &[u8] = &
+    [
+        0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x85, 0x06, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7, 0x15, 0x00,
+        0x25, 0x01, 0x95, 0x08, 0x75, 0x01, 0x81, 0x02, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x95, 0x05,
+        0x75, 0x01, 0x91, 0x02, 0x95, 0x01, 0x75, 0x03, 0x91, 0x03, 0x05, 0x07, 0x19, 0x00, 0x29, 0x77,
+        0x15, 0x00, 0x25, 0x01, 0x95, 0x78, 0x75, 0x01, 0x81, 0x02, 0xc0,
+    ];
+
+    /// Interface 0 of a Logitech K120 (046d:c31c), as dumped with usbhid-dump.
+    const LOGITECH_K120: &[u8] = &
+    [
+        0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7, 0x15, 0x00, 0x25, 0x01,
+        0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x81, 0x03, 0x95, 0x05, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05,
+        0x91, 0x02, 0x95, 0x01, 0x75, 0x03, 0x91, 0x01, 0x95, 0x06, 0x75, 0x08, 0x15, 0x00, 0x26, 0xff,
+        0x00, 0x05, 0x07, 0x19, 0x00, 0x2a, 0xff, 0x00, 0x81, 0x00, 0xc0,
+    ];
+
+    #[test]
+    fn test_boot_keyboard()
+    {
+        let formats = parse(BOOT_KEYBOARD).unwrap();
+        assert_eq!(1, formats.len());
+        let format = &formats[0];
+        assert_eq!(None, format.report_id);
+        assert_eq!(8, format.length);
+        assert_eq!(Some(0), format.modifiers[0]);
+        assert_eq!(Some(7), format.modifiers[7]);
+        assert_eq!(vec![KeyArray { offset: 16, size: 8, count: 6, logical_minimum: 0, usage_minimum: 0 }], format.arrays);
+        assert!(format.bitmaps.is_empty());
+
+        assert_eq!(Some((0x22, vec![0x04, 0x05])), format.decode(&[0x22, 0, 0x04, 0x05, 0, 0, 0, 0]));
+        // Rollover errors are no keys
+        assert_eq!(Some((0, vec![])), format.decode(&[0, 0, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01]));
+    }
+
+    #[test]
+    fn test_logitech_k120()
+    {
+        let formats = parse(LOGITECH_K120).unwrap();
+        assert_eq!(1, formats.len());
+        let format = &formats[0];
+        assert_eq!(None, format.report_id);
+        assert_eq!(8, format.length);
+        assert_eq!(Some(1), format.modifiers[1]);
+        assert_eq!(vec![KeyArray { offset: 16, size: 8, count: 6, logical_minimum: 0, usage_minimum: 0 }], format.arrays);
+
+        assert_eq!(Some([0x02, 0, 0x0b, 0x0c, 0, 0, 0, 0]), format.boot_report(&[0x02, 0, 0x0b, 0x0c, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_mouse_is_no_keyboard()
+    {
+        assert!(parse(BOOT_MOUSE).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_composite_keyboard()
+    {
+        let formats = parse(COMPOSITE_KEYBOARD).unwrap();
+        assert_eq!(1, formats.len());
+        let format = &formats[0];
+        assert_eq!(Some(1), format.report_id);
+        assert_eq!(8, format.length);
+
+        assert_eq!(Some([0x40, 0, 0x14, 0, 0, 0, 0, 0]), format.boot_report(&[0x01, 0x40, 0, 0x14, 0, 0, 0, 0, 0]));
+        // Volume up on the consumer control report
+        assert_eq!(None, format.boot_report(&[0x02, 0xe9, 0x00]));
+    }
+
+    #[test]
+    fn test_nkro_keyboard()
+    {
+        let formats = parse(NKRO_KEYBOARD).unwrap();
+        assert_eq!(1, formats.len());
+        let format = &formats[0];
+        assert_eq!(Some(6), format.report_id);
+        assert_eq!(16, format.length);
+        assert!(format.arrays.is_empty());
+        assert_eq!(vec![KeyBitmap { offset: 8, usage_minimum: 0, count: 120 }], format.bitmaps);
+
+        // Shift with a, b and seven more keys, a boot report only has room for six of them
+        let mut report = [0u8; 17];
+        report[0] = 6;
+        report[1] = 0x02;
+        report[2] = 0b1111_0000;
+        report[3] = 0b0001_1111;
+        let (modifiers, keys) = format.decode(&report).unwrap();
+        assert_eq!(0x02, modifiers);
+        assert_eq!((0x04..=0x0c).collect::<Vec<u8>>(), keys);
+        assert_eq!(Some([0x02, 0, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09]), format.boot_report(&report));
+    }
+
+    #[test]
+    fn test_modifiers_as_keys()
+    {
+        // An array that sends left shift as a key code
+        let formats = parse(COMPOSITE_KEYBOARD).unwrap();
+        assert_eq!(Some((0x02, vec![0x04])), formats[0].decode(&[0x01, 0, 0, 0xe1, 0x04, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_invalid_descriptors()
+    {
+        // A missing end of the last collection is fine
+        assert!(parse(&BOOT_KEYBOARD[..BOOT_KEYBOARD.len() - 1]).is_ok());
+        // Cut off in the middle of an item
+        assert!(parse(&BOOT_KEYBOARD[..11]).is_err());
+        assert!(parse(&[0xc0]).is_err());
+        assert!(parse(&[0xb4]).is_err());
+        assert!(parse(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_long_reports_are_refused()
+    {
+        // 64 bytes of keys fit, one more bit does not
+        assert!(parse(&[0x75, 0x08, 0x95, 0x40, 0x81, 0x02]).is_ok());
+        assert!(parse(&[0x75, 0x08, 0x95, 0x40, 0x81, 0x02, 0x75, 0x01, 0x95, 0x01, 0x81, 0x02]).is_err());
+        // With a report id there is one byte less
+        assert!(parse(&[0x85, 0x01, 0x75, 0x08, 0x95, 0x40, 0x81, 0x02]).is_err());
+        // Sizes that would overflow the bit offset
+        assert!(parse(&[0x77, 0xff, 0xff, 0xff, 0xff, 0x97, 0xff, 0xff, 0xff, 0xff, 0x81, 0x02]).is_err());
+        assert!(matches!(parse(&[0x75, 0x20, 0x96, 0xff, 0xff, 0x81, 0x00]), Err(DataHandleError::InvalidDescriptor(_))));
+    }
+}
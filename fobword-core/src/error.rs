@@ -24,7 +24,33 @@ pub enum DataHandleError
     LockedData(String),
 
     // Channel recvier error
-    RecvError(std::sync::mpsc::RecvError)
+    RecvError(std::sync::mpsc::RecvError),
+
+    // Channel receiver error when waiting with a timeout
+    RecvTimeoutError(std::sync::mpsc::RecvTimeoutError),
+
+    // An input or host event that locks the device, holds the event for debug
+    Locked(String),
+
+    // Settings that can't be used, holds what is wrong with them
+    InvalidSettings(String),
+
+    // A command line that can't be parsed, holds the message for the user
+    InvalidCommand(String),
+
+    // A HID report descriptor that can't be read, holds what is wrong and where
+    InvalidDescriptor(String),
+
+    // The user pressed Escape at a prompt
+    Cancelled,
+}
+
+impl From<std::sync::mpsc::RecvTimeoutError> for DataHandleError
+{
+    fn from(err: std::sync::mpsc::RecvTimeoutError) -> DataHandleError
+    {
+        DataHandleError::RecvTimeoutError(err)
+    }
 }
 
 impl From<std::sync::mpsc::RecvError> for DataHandleError
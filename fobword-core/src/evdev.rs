@@ -0,0 +1,241 @@
+/// Event type of the separator between two batches of events.
+pub const EV_SYN: u16 = 0x00;
+/// Event type of key presses and releases.
+pub const EV_KEY: u16 = 0x01;
+/// A batch of events is complete.
+pub const SYN_REPORT: u16 = 0;
+/// Events were lost because they were not read in time.
+pub const SYN_DROPPED: u16 = 3;
+
+/// The key state of an evdev keyboard, turned into boot reports so it can be merged with the hidraw keyboards.
+///
+/// Linux key codes are translated to HID usages, keys without one are ignored.
+///
+/// # Examples
+/// ```
+/// # use fobword_core::evdev::{EvdevKeyboard, EV_KEY, EV_SYN, SYN_REPORT};
+/// let mut keyboard = EvdevKeyboard::new();
+/// assert_eq!(None, keyboard.event(EV_KEY, 42, 1)); // Left shift
+/// assert_eq!(None, keyboard.event(EV_KEY, 30, 1)); // A
+/// assert_eq!(Some([0x02, 0, 0x04, 0, 0, 0, 0, 0]), keyboard.event(EV_SYN, SYN_REPORT, 0));
+/// ```
+#[derive(Debug, Default)]
+pub struct EvdevKeyboard
+{
+    modifiers: u8,
+    /// The HID usages of the held keys in the order they were pressed
+    keys: Vec<u8>,
+}
+
+impl EvdevKeyboard
+{
+    /// Create a keyboard with nothing pressed.
+    pub fn new() -> EvdevKeyboard
+    {
+        EvdevKeyboard::default()
+    }
+
+    /// Take one input event, returns the boot report when a batch is complete.
+    ///
+    /// When events were dropped the state is unknown, every key is released then.
+    pub fn event(&mut self, kind: u16, code: u16, value: i32) -> Option<[u8; 8]>
+    {
+        match (kind, code)
+        {
+            (EV_KEY, _) =>
+            {
+                self.key(code, value);
+                None
+            },
+            (EV_SYN, SYN_REPORT) => Some(self.report()),
+            (EV_SYN, SYN_DROPPED) =>
+            {
+                self.modifiers = 0;
+                self.keys.clear();
+                Some(self.report())
+            },
+            _ => None,
+        }
+    }
+
+    /// Press (1) or release (0) a key, repeats (2) change nothing.
+    fn key(&mut self, code: u16, value: i32)
+    {
+        if let Some(bit) = modifier_bit(code)
+        {
+            match value
+            {
+                0 => self.modifiers &= !bit,
+                1 => self.modifiers |= bit,
+                _ => (),
+            }
+        }
+        else if let Some(usage) = hid_usage(code)
+        {
+            match value
+            {
+                0 => self.keys.retain(|key| *key != usage),
+                1 if !self.keys.contains(&usage) => self.keys.push(usage),
+                _ => (),
+            }
+        }
+    }
+
+    /// The held keys as a boot report, the six pressed last when more are held.
+    pub fn report(&self) -> [u8; 8]
+    {
+        let mut report = [0u8; 8];
+        report[0] = self.modifiers;
+        let first = self.keys.len().saturating_sub(6);
+        for (slot, key) in report[2..].iter_mut().zip(self.keys[first..].iter())
+        {
+            *slot = *key;
+        }
+        report
+    }
+}
+
+/// The bit in the HID modifier byte of a Linux modifier key code.
+pub fn modifier_bit(code: u16) -> Option<u8>
+{
+    match code
+    {
+        29 => Some(1 << 0),  // Left control
+        42 => Some(1 << 1),  // Left shift
+        56 => Some(1 << 2),  // Left alt
+        125 => Some(1 << 3), // Left meta
+        97 => Some(1 << 4),  // Right control
+        54 => Some(1 << 5),  // Right shift
+        100 => Some(1 << 6), // Right alt
+        126 => Some(1 << 7), // Right meta
+        _ => None,
+    }
+}
+
+/// The HID usage of a Linux key code, `None` for modifiers and keys a keyboard report can't hold.
+///
+/// The codes are the ones from `linux/input-event-codes.h`.
+pub fn hid_usage(code: u16) -> Option<u8>
+{
+    let usage = match code
+    {
+        1 => 0x29,                                  // Escape
+        2..=10 => 0x1e + (code - 2) as u8,          // 1 to 9
+        11 => 0x27,                                 // 0
+        12 => 0x2d,                                 // Minus
+        13 => 0x2e,                                 // Equal
+        14 => 0x2a,                                 // Backspace
+        15 => 0x2b,                                 // Tab
+        16 => 0x14, 17 => 0x1a, 18 => 0x08, 19 => 0x15, 20 => 0x17, // Q W E R T
+        21 => 0x1c, 22 => 0x18, 23 => 0x0c, 24 => 0x12, 25 => 0x13, // Y U I O P
+        26 => 0x2f,                                 // Left brace
+        27 => 0x30,                                 // Right brace
+        28 => 0x28,                                 // Enter
+        30 => 0x04, 31 => 0x16, 32 => 0x07, 33 => 0x09, 34 => 0x0a, // A S D F G
+        35 => 0x0b, 36 => 0x0d, 37 => 0x0e, 38 => 0x0f,             // H J K L
+        39 => 0x33,                                 // Semicolon
+        40 => 0x34,                                 // Apostrophe
+        41 => 0x35,                                 // Grave
+        43 => 0x31,                                 // Backslash
+        44 => 0x1d, 45 => 0x1b, 46 => 0x06, 47 => 0x19, 48 => 0x05, // Z X C V B
+        49 => 0x11, 50 => 0x10,                     // N M
+        51 => 0x36,                                 // Comma
+        52 => 0x37,                                 // Dot
+        53 => 0x38,                                 // Slash
+        55 => 0x55,                                 // Keypad asterisk
+        57 => 0x2c,                                 // Space
+        58 => 0x39,                                 // Caps lock
+        59..=68 => 0x3a + (code - 59) as u8,        // F1 to F10
+        69 => 0x53,                                 // Num lock
+        70 => 0x47,                                 // Scroll lock
+        71 => 0x5f, 72 => 0x60, 73 => 0x61,         // Keypad 7 8 9
+        74 => 0x56,                                 // Keypad minus
+        75 => 0x5c, 76 => 0x5d, 77 => 0x5e,         // Keypad 4 5 6
+        78 => 0x57,                                 // Keypad plus
+        79 => 0x59, 80 => 0x5a, 81 => 0x5b,         // Keypad 1 2 3
+        82 => 0x62,                                 // Keypad 0
+        83 => 0x63,                                 // Keypad dot
+        86 => 0x64,                                 // The extra key next to left shift on ISO keyboards
+        87 => 0x44,                                 // F11
+        88 => 0x45,                                 // F12
+        89 => 0x87,                                 // Ro
+        96 => 0x58,                                 // Keypad enter
+        98 => 0x54,                                 // Keypad slash
+        99 => 0x46,                                 // Print screen
+        102 => 0x4a,                                // Home
+        103 => 0x52,                                // Up
+        104 => 0x4b,                                // Page up
+        105 => 0x50,                                // Left
+        106 => 0x4f,                                // Right
+        107 => 0x4d,                                // End
+        108 => 0x51,                                // Down
+        109 => 0x4e,                                // Page down
+        110 => 0x49,                                // Insert
+        111 => 0x4c,                                // Delete
+        117 => 0x67,                                // Keypad equal
+        119 => 0x48,                                // Pause
+        121 => 0x85,                                // Keypad comma
+        124 => 0x89,                                // Yen
+        127 => 0x65,                                // Compose, the menu key
+        183..=194 => 0x68 + (code - 183) as u8,     // F13 to F24
+        _ => return None,
+    };
+    Some(usage)
+}
+
+#[cfg(test)]
+mod evdevtests
+{
+    use super::*;
+
+    #[test]
+    fn test_key_codes()
+    {
+        assert_eq!(Some(0x04), hid_usage(30));
+        assert_eq!(Some(0x1e), hid_usage(2));
+        assert_eq!(Some(0x26), hid_usage(10));
+        assert_eq!(Some(0x43), hid_usage(68));
+        assert_eq!(Some(0x73), hid_usage(194));
+        assert_eq!(None, hid_usage(42));
+        assert_eq!(None, hid_usage(0x110)); // Left mouse button
+        assert_eq!(Some(0x40), modifier_bit(100));
+    }
+
+    #[test]
+    fn test_reports_after_sync()
+    {
+        let mut keyboard = EvdevKeyboard::new();
+        assert_eq!(None, keyboard.event(EV_KEY, 100, 1));
+        assert_eq!(None, keyboard.event(EV_KEY, 16, 1));
+        assert_eq!(Some([0x40, 0, 0x14, 0, 0, 0, 0, 0]), keyboard.event(EV_SYN, SYN_REPORT, 0));
+
+        // Repeats change nothing
+        keyboard.event(EV_KEY, 16, 2);
+        keyboard.event(EV_KEY, 100, 0);
+        assert_eq!(Some([0, 0, 0x14, 0, 0, 0, 0, 0]), keyboard.event(EV_SYN, SYN_REPORT, 0));
+        keyboard.event(EV_KEY, 16, 0);
+        assert_eq!(Some([0; 8]), keyboard.event(EV_SYN, SYN_REPORT, 0));
+    }
+
+    #[test]
+    fn test_last_six_keys()
+    {
+        let mut keyboard = EvdevKeyboard::new();
+        for code in 30..=36
+        {
+            keyboard.event(EV_KEY, code, 1);
+        }
+        assert_eq!([0, 0, 0x16, 0x07, 0x09, 0x0a, 0x0b, 0x0d], keyboard.report());
+    }
+
+    #[test]
+    fn test_dropped_events_release_everything()
+    {
+        let mut keyboard = EvdevKeyboard::new();
+        keyboard.event(EV_KEY, 29, 1);
+        keyboard.event(EV_KEY, 30, 1);
+        assert_eq!(Some([0; 8]), keyboard.event(EV_SYN, SYN_DROPPED, 0));
+        // Other event types are ignored
+        assert_eq!(None, keyboard.event(0x04, 4, 30));
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// One of the eight modifier keys, in the order of their bits in the first byte of a HID report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModifierKey
+{
+    LeftControl,
+    LeftShift,
+    LeftAlt,
+    LeftGui,
+    RightControl,
+    RightShift,
+    RightAlt,
+    RightGui,
+}
+
+impl ModifierKey
+{
+    /// Every modifier key, from the lowest bit to the highest.
+    pub const ALL: [ModifierKey; 8] = [
+        ModifierKey::LeftControl,
+        ModifierKey::LeftShift,
+        ModifierKey::LeftAlt,
+        ModifierKey::LeftGui,
+        ModifierKey::RightControl,
+        ModifierKey::RightShift,
+        ModifierKey::RightAlt,
+        ModifierKey::RightGui,
+    ];
+
+    /// The bit of the key in the modifier byte.
+    pub fn bit(self) -> u8
+    {
+        1 << self as u8
+    }
+
+    /// Check if the key is on the right side of the keyboard.
+    pub fn is_right(self) -> bool
+    {
+        self.bit() & 0xF0 != 0
+    }
+
+    /// The keys set in a modifier byte, from the lowest bit to the highest.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::input::ModifierKey;
+    /// let keys: Vec<ModifierKey> = ModifierKey::in_byte(0x42).collect();
+    /// assert_eq!(vec![ModifierKey::LeftShift, ModifierKey::RightAlt], keys);
+    /// ```
+    pub fn in_byte(modifiers: u8) -> impl Iterator<Item = ModifierKey>
+    {
+        ModifierKey::ALL.iter().copied().filter(move |key| modifiers & key.bit() != 0)
+    }
+}
+
+/// Something that happened on the keyboards or the host.
+#[derive(Debug, PartialEq)]
+pub enum Events
+{
+    /// One modifier key was released
+    ModifierUp(ModifierKey),
+    /// One modifier key was pressed
+    ModifierDown(ModifierKey),
+    KeyUp(u8),
+    KeyDown(u8),
+    /// A keyboard that was being read from has been unplugged
+    DeviceRemoved(PathBuf),
+    /// The host put the usb gadget in suspend
+    HostSuspended,
+    /// The usb gadget is no longer attached to a host
+    HostDisconnected,
+}
+
+/// An event with the moment it was read.
+#[derive(Debug, PartialEq)]
+pub struct InputEvent
+{
+    pub event: Events,
+    pub time: Instant,
+}
+
+impl InputEvent
+{
+    pub fn new(event: Events, time: Instant) -> InputEvent
+    {
+        InputEvent { event, time }
+    }
+
+    /// An event that happened just now.
+    pub fn now(event: Events) -> InputEvent
+    {
+        InputEvent::new(event, Instant::now())
+    }
+}
+
+/// The keys held on every keyboard, merged as if they were one keyboard.
+///
+/// Every keyboard keeps its own last report. A key is down while any keyboard holds it,
+/// and a modifier bit is set while any keyboard sets it.
+///
+/// # Examples
+/// ```
+/// # use std::path::Path;
+/// # use fobword_core::input::{Events, InputState};
+/// let mut state = InputState::new();
+/// let keyboard = Path::new("/dev/hidraw0");
+/// let other = Path::new("/dev/hidraw1");
+///
+/// assert_eq!(vec![Events::KeyDown(0x04)], state.update(keyboard, &[0, 0, 0x04, 0, 0, 0, 0, 0]));
+/// // Already held on the first keyboard
+/// assert!(state.update(other, &[0, 0, 0x04, 0, 0, 0, 0, 0]).is_empty());
+/// assert!(state.remove(keyboard).is_empty());
+/// assert_eq!(vec![Events::KeyUp(0x04)], state.remove(other));
+/// ```
+#[derive(Debug, Default)]
+pub struct InputState
+{
+    reports: HashMap<PathBuf, [u8; 8]>,
+    /// The merged keys in the order they were pressed
+    held: Vec<u8>,
+}
+
+impl InputState
+{
+    /// Create a state without keyboards.
+    pub fn new() -> InputState
+    {
+        InputState::default()
+    }
+
+    /// Take a new boot report from a keyboard, returns the changes to the merged state.
+    ///
+    /// Byte 0 of the report holds the modifiers and bytes 2 to 7 the key codes, shorter reports are padded with zeros.
+    /// Every modifier that changed gets its own event, releases before presses.
+    pub fn update(&mut self, device: &Path, report: &[u8]) -> Vec<Events>
+    {
+        let mut new_report = [0u8; 8];
+        let length = report.len().min(new_report.len());
+        new_report[..length].copy_from_slice(&report[..length]);
+
+        let old_modifiers = self.modifiers();
+        let old_report = self.reports.insert(device.to_path_buf(), new_report).unwrap_or_default();
+
+        let mut events = Vec::new();
+        let new_modifiers = self.modifiers();
+        events.extend(ModifierKey::in_byte(old_modifiers & !new_modifiers).map(Events::ModifierUp));
+        events.extend(ModifierKey::in_byte(new_modifiers & !old_modifiers).map(Events::ModifierDown));
+
+        for key in InputState::keys_of(&new_report).filter(|key| !InputState::keys_of(&old_report).any(|old| old == *key))
+        {
+            if !self.held.contains(&key)
+            {
+                self.held.push(key);
+                events.push(Events::KeyDown(key));
+            }
+        }
+        for key in InputState::keys_of(&old_report).filter(|key| !InputState::keys_of(&new_report).any(|new| new == *key))
+        {
+            if !self.reports.values().any(|report| InputState::keys_of(report).any(|held| held == key))
+            {
+                self.held.retain(|held| *held != key);
+                events.push(Events::KeyUp(key));
+            }
+        }
+        events
+    }
+
+    /// Forget a keyboard, returns the releases of the keys only it was holding.
+    pub fn remove(&mut self, device: &Path) -> Vec<Events>
+    {
+        let events = self.update(device, &[0u8; 8]);
+        self.reports.remove(device);
+        events
+    }
+
+    /// The merged modifier byte.
+    pub fn modifiers(&self) -> u8
+    {
+        self.reports.values().fold(0, |modifiers, report| modifiers | report[0])
+    }
+
+    /// The merged keys in the order they were pressed.
+    pub fn keys(&self) -> &[u8]
+    {
+        &self.held
+    }
+
+    /// The keyboards that sent a report.
+    pub fn devices(&self) -> impl Iterator<Item = &Path>
+    {
+        self.reports.keys().map(PathBuf::as_path)
+    }
+
+    /// The key codes in a report, without the empty slots.
+    fn keys_of(report: &[u8; 8]) -> impl Iterator<Item = u8> + '_
+    {
+        report[2..].iter().copied().filter(|key| *key != 0)
+    }
+}
+
+#[cfg(test)]
+mod inputtests
+{
+    use super::*;
+
+    const FIRST: &str = "/dev/hidraw0";
+    const SECOND: &str = "/dev/hidraw1";
+
+    fn report(modifiers: u8, keys: &[u8]) -> [u8; 8]
+    {
+        let mut report = [0u8; 8];
+        report[0] = modifiers;
+        report[2..2 + keys.len()].copy_from_slice(keys);
+        report
+    }
+
+    use ModifierKey::*;
+
+    #[test]
+    fn test_modifier_key_bits()
+    {
+        for (index, key) in ModifierKey::ALL.iter().enumerate()
+        {
+            assert_eq!(1 << index, key.bit());
+            assert_eq!(index >= 4, key.is_right());
+        }
+        assert_eq!(ModifierKey::ALL.to_vec(), ModifierKey::in_byte(0xFF).collect::<Vec<_>>());
+        assert_eq!(0, ModifierKey::in_byte(0).count());
+    }
+
+    #[test]
+    fn test_single_keyboard()
+    {
+        let mut state = InputState::new();
+        let first = Path::new(FIRST);
+        assert_eq!(vec![Events::ModifierDown(LeftShift), Events::KeyDown(0x04)], state.update(first, &report(0x02, &[0x04])));
+        assert_eq!(vec![Events::KeyDown(0x05)], state.update(first, &report(0x02, &[0x04, 0x05])));
+        assert_eq!(vec![Events::ModifierUp(LeftShift), Events::KeyUp(0x04)], state.update(first, &report(0, &[0x05])));
+        assert_eq!(vec![Events::KeyUp(0x05)], state.update(first, &report(0, &[])));
+        assert!(state.keys().is_empty());
+    }
+
+    #[test]
+    fn test_keys_held_on_two_keyboards()
+    {
+        let mut state = InputState::new();
+        let (first, second) = (Path::new(FIRST), Path::new(SECOND));
+        state.update(first, &report(0, &[0x04]));
+        assert_eq!(vec![Events::KeyDown(0x05)], state.update(second, &report(0, &[0x04, 0x05])));
+        assert_eq!(&[0x04, 0x05], state.keys());
+
+        // Still held on the second keyboard
+        assert!(state.update(first, &report(0, &[])).is_empty());
+        assert_eq!(vec![Events::KeyUp(0x04), Events::KeyUp(0x05)], state.update(second, &report(0, &[])));
+    }
+
+    #[test]
+    fn test_modifiers_are_merged()
+    {
+        let mut state = InputState::new();
+        let (first, second) = (Path::new(FIRST), Path::new(SECOND));
+        assert_eq!(vec![Events::ModifierDown(LeftShift)], state.update(first, &report(0x02, &[])));
+        assert_eq!(vec![Events::ModifierDown(LeftControl)], state.update(second, &report(0x03, &[])));
+        assert_eq!(0x03, state.modifiers());
+
+        assert!(state.update(first, &report(0, &[])).is_empty());
+        assert_eq!(vec![Events::ModifierUp(LeftControl), Events::ModifierUp(LeftShift)], state.update(second, &report(0, &[])));
+    }
+
+    #[test]
+    fn test_modifier_transitions()
+    {
+        let mut state = InputState::new();
+        let first = Path::new(FIRST);
+        // Nothing changed
+        assert!(state.update(first, &report(0, &[])).is_empty());
+        // Several pressed at once
+        assert_eq!(vec![Events::ModifierDown(LeftShift), Events::ModifierDown(RightAlt)], state.update(first, &report(0x42, &[])));
+        assert!(state.update(first, &report(0x42, &[])).is_empty());
+        // One released while another is pressed, this used to come out as a single subtraction
+        assert_eq!(vec![Events::ModifierUp(LeftShift), Events::ModifierDown(LeftControl)], state.update(first, &report(0x41, &[])));
+        // Left and right of the same modifier are separate keys
+        assert_eq!(vec![Events::ModifierDown(RightControl)], state.update(first, &report(0x51, &[])));
+        assert_eq!(vec![Events::ModifierUp(LeftControl)], state.update(first, &report(0x50, &[])));
+        // Several released at once
+        assert_eq!(vec![Events::ModifierUp(RightControl), Events::ModifierUp(RightAlt)], state.update(first, &report(0, &[])));
+        assert_eq!(0, state.modifiers());
+    }
+
+    #[test]
+    fn test_every_modifier_bit()
+    {
+        let mut state = InputState::new();
+        let first = Path::new(FIRST);
+        for key in ModifierKey::ALL.iter().copied()
+        {
+            assert_eq!(vec![Events::ModifierDown(key)], state.update(first, &report(key.bit(), &[])));
+            assert_eq!(vec![Events::ModifierUp(key)], state.update(first, &report(0, &[])));
+        }
+        // Moving from one bit to the next releases the old one first
+        state.update(first, &report(LeftControl.bit(), &[]));
+        for pair in ModifierKey::ALL.windows(2)
+        {
+            assert_eq!(vec![Events::ModifierUp(pair[0]), Events::ModifierDown(pair[1])], state.update(first, &report(pair[1].bit(), &[])));
+        }
+    }
+
+    #[test]
+    fn test_remove_releases_keys()
+    {
+        let mut state = InputState::new();
+        let (first, second) = (Path::new(FIRST), Path::new(SECOND));
+        state.update(first, &report(0x02, &[0x04, 0x06]));
+        state.update(second, &report(0, &[0x06]));
+
+        assert_eq!(vec![Events::ModifierUp(LeftShift), Events::KeyUp(0x04)], state.remove(first));
+        assert_eq!(&[0x06], state.keys());
+        assert_eq!(vec![Path::new(SECOND)], state.devices().collect::<Vec<_>>());
+        assert_eq!(vec![Events::KeyUp(0x06)], state.remove(second));
+        assert!(state.remove(second).is_empty());
+    }
+}
@@ -3,4 +3,13 @@
 //#![warn(missing_debug_implementations, missing_docs)]
 pub mod converter;
 pub mod config;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod lockout;
+pub mod session;
+pub mod command;
+pub mod lineeditor;
+pub mod input;
+pub mod evdev;
+pub mod descriptor;
+pub mod report;
+pub mod pace;
\ No newline at end of file
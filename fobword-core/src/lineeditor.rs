@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+/// Shown where the cursor is, when it is not at the end of the line.
+pub const CURSOR: char = '|';
+/// Shown instead of the characters of a secret.
+pub const MASK: char = '*';
+
+/// How a secret is shown while it is typed.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Mask
+{
+    /// Every character is shown as [`MASK`]
+    #[default]
+    Bullets,
+    /// The character just typed is shown for a moment, the rest as [`MASK`]
+    LastCharacter,
+}
+
+/// A change to the line being edited.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit
+{
+    /// Insert a character before the cursor
+    Insert(char),
+    /// Remove the character before the cursor
+    Backspace,
+    /// Remove the character after the cursor
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    /// Remove the word before the cursor, like Ctrl-W in a shell
+    DeleteWord,
+    /// Remove everything before the cursor, like Ctrl-U in a shell
+    DeleteToStart,
+}
+
+/// A line of text with a cursor, edited one key at a time.
+///
+/// # Examples
+/// ```
+/// # use fobword_core::lineeditor::{Edit, LineEditor};
+/// let mut editor = LineEditor::new();
+/// editor.insert_str("pasword");
+/// editor.apply(Edit::Left);
+/// editor.apply(Edit::Left);
+/// editor.apply(Edit::Left);
+/// editor.apply(Edit::Left);
+/// editor.apply(Edit::Insert('s'));
+///
+/// assert_eq!("password", editor.text());
+/// assert_eq!("pass|word", editor.display());
+/// ```
+#[derive(Debug, Default)]
+pub struct LineEditor
+{
+    text: Vec<char>,
+    cursor: usize,
+}
+
+impl LineEditor
+{
+    /// Create an empty line.
+    pub fn new() -> LineEditor
+    {
+        LineEditor::default()
+    }
+
+    /// Change the line.
+    pub fn apply(&mut self, edit: Edit)
+    {
+        match edit
+        {
+            Edit::Insert(c) =>
+            {
+                self.text.insert(self.cursor, c);
+                self.cursor += 1;
+            },
+            Edit::Backspace if self.cursor > 0 =>
+            {
+                self.cursor -= 1;
+                self.text.remove(self.cursor);
+            },
+            Edit::Delete if self.cursor < self.text.len() =>
+            {
+                self.text.remove(self.cursor);
+            },
+            Edit::Left => self.cursor = self.cursor.saturating_sub(1),
+            Edit::Right => self.cursor = (self.cursor + 1).min(self.text.len()),
+            Edit::Home => self.cursor = 0,
+            Edit::End => self.cursor = self.text.len(),
+            Edit::DeleteWord =>
+            {
+                let before = &self.text[..self.cursor];
+                let spaces = before.iter().rev().take_while(|c| c.is_whitespace()).count();
+                let word = before.iter().rev().skip(spaces).take_while(|c| !c.is_whitespace()).count();
+                let start = self.cursor - spaces - word;
+                self.text.drain(start..self.cursor);
+                self.cursor = start;
+            },
+            Edit::DeleteToStart =>
+            {
+                self.text.drain(..self.cursor);
+                self.cursor = 0;
+            },
+            Edit::Backspace | Edit::Delete => (),
+        }
+    }
+
+    /// Insert text before the cursor.
+    pub fn insert_str(&mut self, text: &str)
+    {
+        for c in text.chars()
+        {
+            self.apply(Edit::Insert(c));
+        }
+    }
+
+    /// The line without the cursor.
+    pub fn text(&self) -> String
+    {
+        self.text.iter().collect()
+    }
+
+    /// The position of the cursor in characters.
+    pub fn cursor(&self) -> usize
+    {
+        self.cursor
+    }
+
+    /// Check if the cursor is after the last character.
+    pub fn at_end(&self) -> bool
+    {
+        self.cursor == self.text.len()
+    }
+
+    /// The line as it is shown, with the [`CURSOR`] marker unless the cursor is at the end.
+    pub fn display(&self) -> String
+    {
+        self.with_cursor(self.text.clone())
+    }
+
+    /// The line with every character replaced by [`MASK`], except the one before the cursor when `reveal_last` is set.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::lineeditor::LineEditor;
+    /// let mut editor = LineEditor::new();
+    /// editor.insert_str("secret");
+    /// assert_eq!("******", editor.display_masked(false));
+    /// assert_eq!("*****t", editor.display_masked(true));
+    /// ```
+    pub fn display_masked(&self, reveal_last: bool) -> String
+    {
+        let masked = self.text.iter().enumerate()
+            .map(|(index, c)| if reveal_last && index + 1 == self.cursor { *c } else { MASK })
+            .collect();
+        self.with_cursor(masked)
+    }
+
+    fn with_cursor(&self, mut characters: Vec<char>) -> String
+    {
+        if !self.at_end()
+        {
+            characters.insert(self.cursor, CURSOR);
+        }
+        characters.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod lineeditortests
+{
+    use super::*;
+
+    fn editor_at(text: &str, cursor: usize) -> LineEditor
+    {
+        let mut editor = LineEditor::new();
+        editor.insert_str(text);
+        editor.cursor = cursor;
+        editor
+    }
+
+    #[test]
+    fn test_insert_at_cursor()
+    {
+        let mut editor = editor_at("ac", 1);
+        editor.apply(Edit::Insert('b'));
+        assert_eq!("abc", editor.text());
+        assert_eq!(2, editor.cursor());
+    }
+
+    #[test]
+    fn test_backspace_and_delete()
+    {
+        let mut editor = editor_at("abcd", 2);
+        editor.apply(Edit::Backspace);
+        assert_eq!("acd", editor.text());
+        editor.apply(Edit::Delete);
+        assert_eq!("ad", editor.text());
+        assert_eq!(1, editor.cursor());
+
+        let mut editor = editor_at("ab", 0);
+        editor.apply(Edit::Backspace);
+        editor.apply(Edit::End);
+        editor.apply(Edit::Delete);
+        assert_eq!("ab", editor.text());
+    }
+
+    #[test]
+    fn test_cursor_movement()
+    {
+        let mut editor = editor_at("abc", 3);
+        editor.apply(Edit::Right);
+        assert_eq!(3, editor.cursor());
+        editor.apply(Edit::Left);
+        assert_eq!(2, editor.cursor());
+        editor.apply(Edit::Home);
+        editor.apply(Edit::Left);
+        assert_eq!(0, editor.cursor());
+        editor.apply(Edit::End);
+        assert!(editor.at_end());
+    }
+
+    #[test]
+    fn test_delete_word()
+    {
+        let mut editor = editor_at(":gen github  24", 15);
+        editor.apply(Edit::DeleteWord);
+        assert_eq!(":gen github  ", editor.text());
+        editor.apply(Edit::DeleteWord);
+        assert_eq!(":gen ", editor.text());
+
+        let mut editor = editor_at("one two", 3);
+        editor.apply(Edit::DeleteWord);
+        assert_eq!(" two", editor.text());
+        assert_eq!(0, editor.cursor());
+    }
+
+    #[test]
+    fn test_delete_to_start()
+    {
+        let mut editor = editor_at("one two", 4);
+        editor.apply(Edit::DeleteToStart);
+        assert_eq!("two", editor.text());
+        assert_eq!(0, editor.cursor());
+    }
+
+    #[test]
+    fn test_display()
+    {
+        assert_eq!("abc", editor_at("abc", 3).display());
+        assert_eq!("a|bc", editor_at("abc", 1).display());
+        assert_eq!("|é", editor_at("é", 0).display());
+    }
+
+    #[test]
+    fn test_display_masked()
+    {
+        assert_eq!("***", editor_at("abc", 3).display_masked(false));
+        assert_eq!("**c", editor_at("abc", 3).display_masked(true));
+        assert_eq!("*b|*", editor_at("abc", 2).display_masked(true));
+        assert_eq!("|***", editor_at("abc", 0).display_masked(true));
+        assert_eq!("", editor_at("", 0).display_masked(true));
+    }
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+use serde::{ Serialize, Deserialize };
+
+use crate::error::DataHandleError;
+
+/// Attempts that can be made before a delay is added.
+const FREE_ATTEMPTS: u32 = 3;
+/// The longest delay between two attempts, in seconds.
+const MAX_DELAY_SECONDS: u64 = 600;
+
+/// Counter for failed unlock attempts.
+///
+/// The counter is meant to be written to disk after every change, so it survives reboots.
+/// After the first few attempts every failed attempt doubles the delay before the next one.
+///
+/// # Example
+/// ```
+/// # use fobword_core::lockout::Lockout;
+/// let mut lockout = Lockout::new();
+/// lockout.record_failure();
+///
+/// std::thread::sleep(lockout.delay());
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lockout
+{
+    pub failed_attempts: u32,
+}
+
+impl Lockout
+{
+    /// Create a new Lockout without failed attempts.
+    pub fn new() -> Lockout
+    {
+        Lockout { failed_attempts: 0 }
+    }
+
+    /// Count a failed attempt.
+    pub fn record_failure(&mut self)
+    {
+        self.failed_attempts = self.failed_attempts.saturating_add(1);
+    }
+
+    /// Reset the counter after a successful unlock.
+    pub fn reset(&mut self)
+    {
+        self.failed_attempts = 0;
+    }
+
+    /// The time to wait before the next attempt may be made.
+    pub fn delay(&self) -> Duration
+    {
+        if self.failed_attempts < FREE_ATTEMPTS
+        {
+            return Duration::from_secs(0)
+        }
+        let seconds = 1u64.checked_shl(self.failed_attempts - FREE_ATTEMPTS).unwrap_or(MAX_DELAY_SECONDS);
+        Duration::from_secs(seconds.min(MAX_DELAY_SECONDS))
+    }
+
+    /// Check if the failed attempts reached the threshold at which the vault should be erased.
+    ///
+    /// A threshold of `None` never erases.
+    pub fn should_wipe(&self, threshold: Option<u32>) -> bool
+    {
+        threshold.is_some_and(|threshold| self.failed_attempts >= threshold)
+    }
+
+    /// Serialize the counter as a string of YAML.
+    pub fn to_yaml(&self) -> Result<String, DataHandleError>
+    {
+        Ok(serde_yaml::to_string(&self)?)
+    }
+
+    /// Deserialize the counter from a string of YAML.
+    pub fn from_yaml(buffer: &str) -> Result<Self, DataHandleError>
+    {
+        Ok(serde_yaml::from_str(buffer)?)
+    }
+}
+
+#[cfg(test)]
+mod lockouttests
+{
+    use super::*;
+
+    #[test]
+    fn test_no_delay_for_first_attempts()
+    {
+        let mut lockout = Lockout::new();
+        for _ in 0..FREE_ATTEMPTS - 1
+        {
+            lockout.record_failure();
+            assert_eq!(Duration::from_secs(0), lockout.delay());
+        }
+    }
+
+    #[test]
+    fn test_delay_doubles()
+    {
+        let mut lockout = Lockout { failed_attempts: FREE_ATTEMPTS };
+        assert_eq!(Duration::from_secs(1), lockout.delay());
+        lockout.record_failure();
+        assert_eq!(Duration::from_secs(2), lockout.delay());
+        lockout.record_failure();
+        assert_eq!(Duration::from_secs(4), lockout.delay());
+    }
+
+    #[test]
+    fn test_delay_is_capped()
+    {
+        let lockout = Lockout { failed_attempts: 100 };
+        assert_eq!(Duration::from_secs(MAX_DELAY_SECONDS), lockout.delay());
+
+        let lockout = Lockout { failed_attempts: u32::MAX };
+        assert_eq!(Duration::from_secs(MAX_DELAY_SECONDS), lockout.delay());
+    }
+
+    #[test]
+    fn test_should_wipe()
+    {
+        let mut lockout = Lockout { failed_attempts: 9 };
+        assert!(!lockout.should_wipe(None));
+        assert!(!lockout.should_wipe(Some(10)));
+        lockout.record_failure();
+        assert!(lockout.should_wipe(Some(10)));
+        lockout.reset();
+        assert!(!lockout.should_wipe(Some(10)));
+    }
+
+    #[test]
+    fn test_yaml_round_trip()
+    {
+        let lockout = Lockout { failed_attempts: 4 };
+        let yaml = lockout.to_yaml().unwrap();
+        assert_eq!(lockout, Lockout::from_yaml(&yaml).unwrap());
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+use serde::{ Serialize, Deserialize };
+
+/// The kind of host a password is typed on, slow hosts lose keys when they are typed too fast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HostProfile
+{
+    /// As fast as the gadget takes the reports
+    #[default]
+    Default,
+    /// Virtual machines and remote desktops
+    SlowVm,
+    /// BIOS and boot loader screens
+    Bios,
+}
+
+impl HostProfile
+{
+    /// Every profile, in the order they are numbered when the user is asked.
+    pub const ALL: [HostProfile; 3] = [HostProfile::Default, HostProfile::SlowVm, HostProfile::Bios];
+
+    /// The name of the profile, `--name` selects it in commands.
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            HostProfile::Default => "default",
+            HostProfile::SlowVm => "slow-vm",
+            HostProfile::Bios => "bios",
+        }
+    }
+
+    /// Parse a profile as typed by the user, either its number from 1 to 3 or its name.
+    ///
+    /// # Examples
+    /// ```
+    /// # use fobword_core::pace::HostProfile;
+    /// assert_eq!(Some(HostProfile::SlowVm), HostProfile::from_name("2"));
+    /// assert_eq!(Some(HostProfile::Bios), HostProfile::from_name("BIOS"));
+    /// assert_eq!(None, HostProfile::from_name("fast"));
+    /// ```
+    pub fn from_name(name: &str) -> Option<HostProfile>
+    {
+        match name.trim().to_lowercase().as_str()
+        {
+            "1" | "default" => Some(HostProfile::Default),
+            "2" | "slow-vm" | "vm" => Some(HostProfile::SlowVm),
+            "3" | "bios" => Some(HostProfile::Bios),
+            _ => None,
+        }
+    }
+}
+
+/// How fast reports are written to the host, every delay is in milliseconds.
+///
+/// Delays add up, a report with a key pressed waits `report_delay_ms` and `hold_ms`.
+///
+/// # Example
+/// ```
+/// # use std::time::Duration;
+/// # use fobword_core::pace::TypingPace;
+/// let pace = TypingPace { report_delay_ms: 5, char_delay_ms: 20, hold_ms: 10 };
+///
+/// assert_eq!(Duration::from_millis(15), pace.delay_after(true));
+/// assert_eq!(Duration::from_millis(25), pace.delay_after(false));
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypingPace
+{
+    /// Wait after every report
+    pub report_delay_ms: u64,
+    /// Wait after a character is released, when set every character is typed on its own
+    pub char_delay_ms: u64,
+    /// How long keys stay pressed
+    pub hold_ms: u64,
+}
+
+impl TypingPace
+{
+    /// Check if any delay is set.
+    pub fn is_paced(&self) -> bool
+    {
+        *self != TypingPace::default()
+    }
+
+    /// Check if characters have to be typed one at a time instead of several in a report.
+    pub fn one_key_per_report(&self) -> bool
+    {
+        self.char_delay_ms > 0
+    }
+
+    /// How long to wait after writing a report, `pressed` when the report holds a key or modifier.
+    pub fn delay_after(&self, pressed: bool) -> Duration
+    {
+        let extra = if pressed { self.hold_ms } else { self.char_delay_ms };
+        Duration::from_millis(self.report_delay_ms.saturating_add(extra))
+    }
+}
+
+/// The pace of every host profile, profiles left out of the settings keep their default pace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostProfiles
+{
+    pub default: TypingPace,
+    pub slow_vm: TypingPace,
+    pub bios: TypingPace,
+}
+
+impl Default for HostProfiles
+{
+    fn default() -> HostProfiles
+    {
+        HostProfiles
+        {
+            default: TypingPace::default(),
+            slow_vm: TypingPace { report_delay_ms: 8, char_delay_ms: 8, hold_ms: 16 },
+            bios: TypingPace { report_delay_ms: 16, char_delay_ms: 30, hold_ms: 40 },
+        }
+    }
+}
+
+impl HostProfiles
+{
+    /// The pace of a profile.
+    pub fn pace(&self, profile: HostProfile) -> TypingPace
+    {
+        match profile
+        {
+            HostProfile::Default => self.default,
+            HostProfile::SlowVm => self.slow_vm,
+            HostProfile::Bios => self.bios,
+        }
+    }
+}
+
+#[cfg(test)]
+mod pacetests
+{
+    use super::*;
+
+    #[test]
+    fn test_profile_names()
+    {
+        for (number, profile) in HostProfile::ALL.iter().enumerate()
+        {
+            assert_eq!(Some(*profile), HostProfile::from_name(profile.name()));
+            assert_eq!(Some(*profile), HostProfile::from_name(&(number + 1).to_string()));
+        }
+        assert_eq!(None, HostProfile::from_name("4"));
+    }
+
+    #[test]
+    fn test_default_is_not_paced()
+    {
+        let profiles = HostProfiles::default();
+        assert!(!profiles.pace(HostProfile::Default).is_paced());
+        assert_eq!(Duration::from_millis(0), profiles.pace(HostProfile::Default).delay_after(true));
+        assert!(profiles.pace(HostProfile::SlowVm).one_key_per_report());
+        assert!(profiles.pace(HostProfile::Bios).delay_after(true) > profiles.pace(HostProfile::SlowVm).delay_after(true));
+    }
+
+    #[test]
+    fn test_missing_profiles_keep_their_pace()
+    {
+        let profiles: HostProfiles = serde_yaml::from_str("bios:\n  hold_ms: 100\n").unwrap();
+        assert_eq!(HostProfiles::default().slow_vm, profiles.slow_vm);
+        assert_eq!(100, profiles.bios.hold_ms);
+        assert_eq!(0, profiles.bios.report_delay_ms);
+    }
+}
@@ -0,0 +1,509 @@
+use serde::{Deserialize, Serialize};
+
+/// The usage of the first modifier, modifiers pressed as keys go to the modifier byte.
+const FIRST_MODIFIER: u8 = 0xe0;
+/// The usage of the last modifier, the usages after it are reserved and ignored.
+const LAST_MODIFIER: u8 = 0xe7;
+/// What a boot report holds in every key slot when more than six keys are pressed.
+const ERROR_ROLL_OVER: u8 = 0x01;
+/// Key slots in a boot report.
+const BOOT_KEYS: usize = 6;
+/// Keys below the modifiers, the ones an NKRO report has a bit for.
+const KEY_USAGES: usize = FIRST_MODIFIER as usize;
+/// The length of the longest report of any of the protocols.
+pub const MAX_REPORT_LENGTH: usize = 1 + KEY_USAGES / 8;
+
+/// The report descriptor of the boot keyboard, like the usb gadget example of the kernel,
+/// but with key codes up to 0xff so F13 to F24 can be sent.
+const BOOT_DESCRIPTOR: &[u8] = &
+[
+    0x05, 0x01,       // Usage page (generic desktop)
+    0x09, 0x06,       // Usage (keyboard)
+    0xa1, 0x01,       // Collection (application)
+    0x05, 0x07,       //   Usage page (keyboard)
+    0x19, 0xe0,       //   Usage minimum (left control)
+    0x29, 0xe7,       //   Usage maximum (right gui)
+    0x15, 0x00,       //   Logical minimum (0)
+    0x25, 0x01,       //   Logical maximum (1)
+    0x75, 0x01,       //   Report size (1)
+    0x95, 0x08,       //   Report count (8)
+    0x81, 0x02,       //   Input (data, variable, absolute), the modifiers
+    0x95, 0x01,       //   Report count (1)
+    0x75, 0x08,       //   Report size (8)
+    0x81, 0x03,       //   Input (constant), reserved
+    0x95, 0x05,       //   Report count (5)
+    0x75, 0x01,       //   Report size (1)
+    0x05, 0x08,       //   Usage page (leds)
+    0x19, 0x01,       //   Usage minimum (num lock)
+    0x29, 0x05,       //   Usage maximum (kana)
+    0x91, 0x02,       //   Output (data, variable, absolute), the leds
+    0x95, 0x01,       //   Report count (1)
+    0x75, 0x03,       //   Report size (3)
+    0x91, 0x03,       //   Output (constant), padding
+    0x95, 0x06,       //   Report count (6)
+    0x75, 0x08,       //   Report size (8)
+    0x15, 0x00,       //   Logical minimum (0)
+    0x26, 0xff, 0x00, //   Logical maximum (255)
+    0x05, 0x07,       //   Usage page (keyboard)
+    0x19, 0x00,       //   Usage minimum (0)
+    0x2a, 0xff, 0x00, //   Usage maximum (255)
+    0x81, 0x00,       //   Input (data, array), the keys
+    0xc0,             // End collection
+];
+
+/// The report descriptor of the n-key rollover keyboard, a modifier byte and one bit for every key up to 0xdf.
+const NKRO_DESCRIPTOR: &[u8] = &
+[
+    0x05, 0x01,       // Usage page (generic desktop)
+    0x09, 0x06,       // Usage (keyboard)
+    0xa1, 0x01,       // Collection (application)
+    0x05, 0x07,       //   Usage page (keyboard)
+    0x19, 0xe0,       //   Usage minimum (left control)
+    0x29, 0xe7,       //   Usage maximum (right gui)
+    0x15, 0x00,       //   Logical minimum (0)
+    0x25, 0x01,       //   Logical maximum (1)
+    0x75, 0x01,       //   Report size (1)
+    0x95, 0x08,       //   Report count (8)
+    0x81, 0x02,       //   Input (data, variable, absolute), the modifiers
+    0x19, 0x00,       //   Usage minimum (0)
+    0x29, 0xdf,       //   Usage maximum (0xdf)
+    0x95, 0xe0,       //   Report count (224)
+    0x81, 0x02,       //   Input (data, variable, absolute), the keys
+    0x95, 0x05,       //   Report count (5)
+    0x05, 0x08,       //   Usage page (leds)
+    0x19, 0x01,       //   Usage minimum (num lock)
+    0x29, 0x05,       //   Usage maximum (kana)
+    0x91, 0x02,       //   Output (data, variable, absolute), the leds
+    0x95, 0x01,       //   Report count (1)
+    0x75, 0x03,       //   Report size (3)
+    0x91, 0x03,       //   Output (constant), padding
+    0xc0,             // End collection
+];
+
+/// The kind of report written to the usb gadget, the gadget has to be set up with the matching descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ReportProtocol
+{
+    /// 8 byte reports with six keys, every host understands these, BIOS setups included
+    #[default]
+    Boot,
+    /// A bitmap with a bit for every key, any number of keys can be held
+    Nkro,
+}
+
+impl ReportProtocol
+{
+    /// The report descriptor the gadget needs, written to `report_desc` of the hid function.
+    pub fn descriptor(self) -> &'static [u8]
+    {
+        match self
+        {
+            ReportProtocol::Boot => BOOT_DESCRIPTOR,
+            ReportProtocol::Nkro => NKRO_DESCRIPTOR,
+        }
+    }
+
+    /// The length of a report in bytes, written to `report_length` of the hid function.
+    pub fn length(self) -> usize
+    {
+        match self
+        {
+            ReportProtocol::Boot => 2 + BOOT_KEYS,
+            ReportProtocol::Nkro => MAX_REPORT_LENGTH,
+        }
+    }
+}
+
+/// A single keyboard report of one of the protocols, kept on the stack.
+///
+/// # Examples
+/// ```
+/// # use fobword_core::report::{KeyboardReport, ReportProtocol};
+/// let mut report = KeyboardReport::new(ReportProtocol::Boot);
+/// report.set_modifiers(0x02);
+/// assert!(report.press(0x04));
+/// assert_eq!(&[0x02, 0, 0x04, 0, 0, 0, 0, 0], report.as_bytes());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyboardReport
+{
+    protocol: ReportProtocol,
+    bytes: [u8; MAX_REPORT_LENGTH],
+}
+
+impl KeyboardReport
+{
+    /// Create a report with nothing pressed.
+    pub fn new(protocol: ReportProtocol) -> KeyboardReport
+    {
+        KeyboardReport { protocol, bytes: [0; MAX_REPORT_LENGTH] }
+    }
+
+    pub fn protocol(&self) -> ReportProtocol
+    {
+        self.protocol
+    }
+
+    /// The bytes written to the gadget.
+    pub fn as_bytes(&self) -> &[u8]
+    {
+        &self.bytes[..self.protocol.length()]
+    }
+
+    pub fn modifiers(&self) -> u8
+    {
+        self.bytes[0]
+    }
+
+    /// Set the modifier byte.
+    pub fn set_modifiers(&mut self, modifiers: u8)
+    {
+        self.bytes[0] = modifiers;
+    }
+
+    /// Press a key, modifier usages set their bit in the modifier byte and the reserved usages after them are ignored.
+    ///
+    /// Returns `false` when there is no room left for the key.
+    pub fn press(&mut self, key: u8) -> bool
+    {
+        if (FIRST_MODIFIER..=LAST_MODIFIER).contains(&key)
+        {
+            self.bytes[0] |= 1 << (key - FIRST_MODIFIER);
+            return true
+        }
+        if key == 0 || key > LAST_MODIFIER || self.holds(key)
+        {
+            return true
+        }
+        match self.protocol
+        {
+            ReportProtocol::Boot =>
+            {
+                match self.bytes[2..2 + BOOT_KEYS].iter_mut().find(|slot| **slot == 0)
+                {
+                    Some(slot) =>
+                    {
+                        *slot = key;
+                        true
+                    },
+                    None => false,
+                }
+            },
+            ReportProtocol::Nkro =>
+            {
+                self.bytes[1 + key as usize / 8] |= 1 << (key % 8);
+                true
+            },
+        }
+    }
+
+    pub fn release(&mut self, key: u8)
+    {
+        if key >= FIRST_MODIFIER
+        {
+            if key <= LAST_MODIFIER
+            {
+                self.bytes[0] &= !(1 << (key - FIRST_MODIFIER));
+            }
+            return
+        }
+        match self.protocol
+        {
+            ReportProtocol::Boot =>
+            {
+                // Keep the keys in the first slots
+                let slots = &mut self.bytes[2..2 + BOOT_KEYS];
+                if let Some(index) = slots.iter().position(|slot| *slot == key)
+                {
+                    slots[index..].rotate_left(1);
+                    slots[BOOT_KEYS - 1] = 0;
+                }
+            },
+            ReportProtocol::Nkro => self.bytes[1 + key as usize / 8] &= !(1 << (key % 8)),
+        }
+    }
+
+    /// Check if a key is pressed.
+    pub fn holds(&self, key: u8) -> bool
+    {
+        if key == 0 || key >= FIRST_MODIFIER
+        {
+            return false
+        }
+        match self.protocol
+        {
+            ReportProtocol::Boot => self.bytes[2..2 + BOOT_KEYS].contains(&key),
+            ReportProtocol::Nkro => self.bytes[1 + key as usize / 8] & (1 << (key % 8)) != 0,
+        }
+    }
+
+    /// Check if nothing is pressed.
+    pub fn is_empty(&self) -> bool
+    {
+        self.as_bytes().iter().all(|byte| *byte == 0)
+    }
+
+    /// Check if another key would not fit, a boot report has room for six.
+    pub fn is_full(&self) -> bool
+    {
+        match self.protocol
+        {
+            ReportProtocol::Boot => !self.bytes[2..2 + BOOT_KEYS].contains(&0),
+            ReportProtocol::Nkro => false,
+        }
+    }
+
+    /// Tell the host more keys are held than a boot report can hold, with the roll over error in every slot.
+    ///
+    /// The HID specification asks for this so the host ignores the report instead of seeing some of the keys released.
+    pub fn roll_over(&mut self)
+    {
+        if self.protocol == ReportProtocol::Boot
+        {
+            for slot in self.bytes[2..2 + BOOT_KEYS].iter_mut()
+            {
+                *slot = ERROR_ROLL_OVER;
+            }
+        }
+    }
+}
+
+impl AsRef<[u8]> for KeyboardReport
+{
+    fn as_ref(&self) -> &[u8]
+    {
+        self.as_bytes()
+    }
+}
+
+/// Keeps track of pressed keys and builds the report of one of the protocols from them.
+///
+/// Unlike a [`KeyboardReport`] it takes any number of keys, a boot report of more than six says so with [`KeyboardReport::roll_over`].
+///
+/// # Examples
+/// ```
+/// # use fobword_core::report::{ReportBuilder, ReportProtocol};
+/// let mut builder = ReportBuilder::new(ReportProtocol::Boot);
+/// builder.set_modifiers(0x02);
+/// builder.press(0x04);
+/// assert_eq!(&[0x02, 0, 0x04, 0, 0, 0, 0, 0], builder.build().as_bytes());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReportBuilder
+{
+    protocol: ReportProtocol,
+    modifiers: u8,
+    /// A bit for every pressed key
+    keys: [u8; KEY_USAGES / 8],
+}
+
+impl ReportBuilder
+{
+    /// Create a builder with nothing pressed.
+    pub fn new(protocol: ReportProtocol) -> ReportBuilder
+    {
+        ReportBuilder { protocol, modifiers: 0, keys: [0; KEY_USAGES / 8] }
+    }
+
+    pub fn protocol(&self) -> ReportProtocol
+    {
+        self.protocol
+    }
+
+    pub fn modifiers(&self) -> u8
+    {
+        self.modifiers
+    }
+
+    /// Set the modifier byte.
+    pub fn set_modifiers(&mut self, modifiers: u8)
+    {
+        self.modifiers = modifiers;
+    }
+
+    /// Press a key, modifier usages set their bit in the modifier byte and the reserved usages after them are ignored.
+    pub fn press(&mut self, key: u8)
+    {
+        if (FIRST_MODIFIER..=LAST_MODIFIER).contains(&key)
+        {
+            self.modifiers |= 1 << (key - FIRST_MODIFIER);
+        }
+        else if key != 0 && key < FIRST_MODIFIER
+        {
+            self.keys[key as usize / 8] |= 1 << (key % 8);
+        }
+    }
+
+    pub fn release(&mut self, key: u8)
+    {
+        if (FIRST_MODIFIER..=LAST_MODIFIER).contains(&key)
+        {
+            self.modifiers &= !(1 << (key - FIRST_MODIFIER));
+        }
+        else if key < FIRST_MODIFIER
+        {
+            self.keys[key as usize / 8] &= !(1 << (key % 8));
+        }
+    }
+
+    /// Release every key and modifier.
+    pub fn clear(&mut self)
+    {
+        self.modifiers = 0;
+        self.keys = [0; KEY_USAGES / 8];
+    }
+
+    /// Check if a key is pressed.
+    pub fn holds(&self, key: u8) -> bool
+    {
+        key != 0 && key < FIRST_MODIFIER && self.keys[key as usize / 8] & (1 << (key % 8)) != 0
+    }
+
+    /// Check if nothing is pressed.
+    pub fn is_empty(&self) -> bool
+    {
+        self.modifiers == 0 && self.keys.iter().all(|byte| *byte == 0)
+    }
+
+    /// The number of pressed keys, modifiers not included.
+    pub fn len(&self) -> usize
+    {
+        self.keys.iter().map(|byte| byte.count_ones() as usize).sum()
+    }
+
+    /// The report of the pressed keys.
+    pub fn build(&self) -> KeyboardReport
+    {
+        let mut report = KeyboardReport::new(self.protocol);
+        report.set_modifiers(self.modifiers);
+        if self.protocol == ReportProtocol::Boot && self.len() > BOOT_KEYS
+        {
+            report.roll_over();
+            return report
+        }
+        for key in (1..FIRST_MODIFIER).filter(|key| self.holds(*key))
+        {
+            report.press(key);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod reporttests
+{
+    use super::*;
+    use crate::descriptor;
+
+    #[test]
+    fn test_boot_report()
+    {
+        let mut report = KeyboardReport::new(ReportProtocol::Boot);
+        assert!(report.is_empty());
+        assert!(report.press(0xe1));
+        assert!(report.press(0x04));
+        assert!(report.press(0x05));
+        assert!(report.press(0x04));
+        assert_eq!(&[0x02, 0, 0x04, 0x05, 0, 0, 0, 0], report.as_bytes());
+        report.release(0xe1);
+        report.release(0x04);
+        assert_eq!(&[0, 0, 0x05, 0, 0, 0, 0, 0], report.as_bytes());
+        report.release(0x05);
+        assert!(report.is_empty());
+        assert_eq!(KeyboardReport::new(ReportProtocol::Boot), report);
+    }
+
+    #[test]
+    fn test_full_boot_report()
+    {
+        let mut report = KeyboardReport::new(ReportProtocol::Boot);
+        for key in 0x04..0x0a
+        {
+            assert!(report.press(key));
+        }
+        assert!(report.is_full());
+        assert!(!report.press(0x0a));
+        assert!(!report.holds(0x0a));
+        assert_eq!(&[0, 0, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09], report.as_bytes());
+    }
+
+    #[test]
+    fn test_boot_roll_over()
+    {
+        let mut builder = ReportBuilder::new(ReportProtocol::Boot);
+        for key in 0x04..0x0a
+        {
+            builder.press(key);
+        }
+        assert_eq!(&[0, 0, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09], builder.build().as_bytes());
+        builder.press(0x0a);
+        assert_eq!(7, builder.len());
+        assert_eq!(&[0, 0, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01], builder.build().as_bytes());
+        // Back to six, the keys are sent again
+        builder.release(0x04);
+        assert_eq!(&[0, 0, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a], builder.build().as_bytes());
+    }
+
+    #[test]
+    fn test_reserved_usages_are_ignored()
+    {
+        for protocol in [ReportProtocol::Boot, ReportProtocol::Nkro].iter().copied()
+        {
+            let mut builder = ReportBuilder::new(protocol);
+            let mut report = KeyboardReport::new(protocol);
+            for key in 0xe7..=0xff
+            {
+                builder.press(key);
+                assert!(report.press(key));
+            }
+            assert_eq!(0x80, builder.modifiers());
+            assert_eq!(builder.build(), report);
+            for key in 0xe7..=0xff
+            {
+                builder.release(key);
+                report.release(key);
+            }
+            assert!(builder.is_empty());
+            assert!(report.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_nkro_report()
+    {
+        let mut builder = ReportBuilder::new(ReportProtocol::Nkro);
+        for key in 0x04..0x14
+        {
+            builder.press(key);
+        }
+        builder.press(0xdf);
+        let report = builder.build();
+        assert!(!report.is_full());
+        assert!(report.holds(0xdf));
+        let bytes = report.as_bytes();
+        assert_eq!(ReportProtocol::Nkro.length(), bytes.len());
+        assert_eq!(0b1111_0000, bytes[1]);
+        assert_eq!(0b1111_1111, bytes[2]);
+        assert_eq!(0b0000_1111, bytes[3]);
+        assert_eq!(0b1000_0000, bytes[28]);
+    }
+
+    #[test]
+    fn test_reports_match_their_descriptor()
+    {
+        for protocol in [ReportProtocol::Boot, ReportProtocol::Nkro].iter().copied()
+        {
+            let formats = descriptor::parse(protocol.descriptor()).unwrap();
+            assert_eq!(1, formats.len());
+            assert_eq!(protocol.length(), formats[0].length);
+
+            let mut builder = ReportBuilder::new(protocol);
+            builder.set_modifiers(0x41);
+            for key in [0x73, 0x04, 0x28].iter()
+            {
+                builder.press(*key);
+            }
+            assert_eq!(Some((0x41, vec![0x04, 0x28, 0x73])), formats[0].decode(builder.build().as_bytes()));
+        }
+    }
+}
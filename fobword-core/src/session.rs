@@ -0,0 +1,255 @@
+use crate::config::{Data, LockedData};
+use crate::error::DataHandleError;
+
+/// The state of a [`Session`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState
+{
+    /// Only the encrypted data is available
+    Locked,
+    /// The data is decrypted and the same as the encrypted data
+    Unlocked,
+    /// The data is decrypted and has changes that are not encrypted yet
+    Dirty,
+}
+
+/// A vault that keeps track of its decrypted data and unsaved changes.
+///
+/// Changes to the data go through [`Session::modify`], so the session knows when there is something to save.
+/// With autosave the password is kept while unlocked and every change is encrypted straight away.
+///
+/// # Examples
+/// ```
+/// # use fobword_core::config::{DataInformation, LockedData};
+/// # use fobword_core::error::DataHandleError;
+/// # use fobword_core::session::{Session, SessionState};
+/// let mut session = Session::new(LockedData::new("password")?);
+/// session.unlock("password", false)?;
+///
+/// session.modify(|data| data.insert(String::from("website"), DataInformation::new(None, None, String::from("secret"))))?;
+/// assert_eq!(SessionState::Dirty, session.state());
+///
+/// session.save("password")?;
+/// assert_eq!(SessionState::Unlocked, session.state());
+/// # Ok::<(), DataHandleError>(())
+/// ```
+#[derive(Debug)]
+pub struct Session
+{
+    locked_data: LockedData,
+    data: Option<Data>,
+    dirty: bool,
+    /// Only kept while unlocked with autosave
+    password: Option<String>,
+    /// Set when autosave encrypted a change that still has to be written to disk
+    saved: bool,
+}
+
+impl Session
+{
+    /// Create a locked session.
+    pub fn new(locked_data: LockedData) -> Session
+    {
+        Session { locked_data, data: None, dirty: false, password: None, saved: false }
+    }
+
+    /// The current state of the session.
+    pub fn state(&self) -> SessionState
+    {
+        match (&self.data, self.dirty)
+        {
+            (None, _) => SessionState::Locked,
+            (Some(_), false) => SessionState::Unlocked,
+            (Some(_), true) => SessionState::Dirty,
+        }
+    }
+
+    /// Decrypt the data with the password.
+    ///
+    /// With `autosave` the password is kept until the session is locked, to encrypt every change.
+    ///
+    /// # Errors
+    /// The errors of [`LockedData::unlock`]
+    pub fn unlock(&mut self, password: &str, autosave: bool) -> Result<(), DataHandleError>
+    {
+        self.data = Some(self.locked_data.unlock(password)?);
+        self.dirty = false;
+        self.password = if autosave { Some(password.to_owned()) } else { None };
+        Ok(())
+    }
+
+    /// Drop the decrypted data, discarding unsaved changes.
+    pub fn lock(&mut self)
+    {
+        self.data = None;
+        self.dirty = false;
+        self.password = None;
+    }
+
+    /// Encrypt the changes with the password, the session stays unlocked.
+    ///
+    /// # Errors
+    /// The errors of [`LockedData::lock`], or `LockedData` when the session is locked
+    pub fn save(&mut self, password: &str) -> Result<(), DataHandleError>
+    {
+        let data = self.data.clone().ok_or_else(Session::locked_error)?;
+        self.locked_data.lock(password, data)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// The decrypted data, `None` when locked.
+    pub fn data(&self) -> Option<&Data>
+    {
+        self.data.as_ref()
+    }
+
+    /// Change the decrypted data, marking the session dirty.
+    ///
+    /// With autosave the change is encrypted straight away and [`Session::take_saved`] will return true.
+    ///
+    /// # Errors
+    /// `LockedData` when the session is locked, or the errors of [`LockedData::lock`] when autosaving
+    pub fn modify<F, R>(&mut self, change: F) -> Result<R, DataHandleError>
+    where F: FnOnce(&mut Data) -> R
+    {
+        let data = self.data.as_mut().ok_or_else(Session::locked_error)?;
+        let result = change(data);
+        self.dirty = true;
+
+        if let Some(password) = self.password.clone()
+        {
+            self.save(&password)?;
+            self.saved = true;
+        }
+        Ok(result)
+    }
+
+    /// Returns true once after autosave encrypted a change, so the caller can write it to disk.
+    pub fn take_saved(&mut self) -> bool
+    {
+        std::mem::replace(&mut self.saved, false)
+    }
+
+    /// Change the password of the slot opened by `old_password`.
+    ///
+    /// The password kept for autosave is replaced too, so later changes are encrypted with the new one.
+    ///
+    /// # Errors
+    /// The errors of [`LockedData::change_password`]
+    pub fn change_password(&mut self, old_password: &str, new_password: &str) -> Result<(), DataHandleError>
+    {
+        self.locked_data.change_password(old_password, new_password)?;
+        if self.password.as_deref() == Some(old_password)
+        {
+            self.password = Some(new_password.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Check the master password without changing the session, the duress password is refused.
+    pub fn check_master_password(&self, password: &str) -> Result<(), DataHandleError>
+    {
+        self.locked_data.check_master_password(password)
+    }
+
+    /// The encrypted data, as it should be written to disk.
+    pub fn locked_data(&self) -> &LockedData
+    {
+        &self.locked_data
+    }
+
+    /// The encrypted data, for changing passwords.
+    pub fn locked_data_mut(&mut self) -> &mut LockedData
+    {
+        &mut self.locked_data
+    }
+
+    fn locked_error() -> DataHandleError
+    {
+        DataHandleError::LockedData("the session is locked".to_string())
+    }
+}
+
+#[cfg(test)]
+mod sessiontests
+{
+    use super::*;
+    use crate::config::DataInformation;
+
+    fn insert(session: &mut Session) -> Result<Option<DataInformation>, DataHandleError>
+    {
+        session.modify(|data| data.insert(String::from("Website"), DataInformation::new(None, None, String::from("some_password"))))
+    }
+
+    #[test]
+    fn test_session_states()
+    {
+        let mut session = Session::new(LockedData::new("password").unwrap());
+        assert_eq!(SessionState::Locked, session.state());
+
+        session.unlock("password", false).unwrap();
+        assert_eq!(SessionState::Unlocked, session.state());
+
+        insert(&mut session).unwrap();
+        assert_eq!(SessionState::Dirty, session.state());
+
+        session.save("password").unwrap();
+        assert_eq!(SessionState::Unlocked, session.state());
+        assert!(!session.take_saved());
+
+        session.lock();
+        assert_eq!(SessionState::Locked, session.state());
+        assert!(session.data().is_none());
+    }
+
+    #[test]
+    fn test_lock_discards_changes()
+    {
+        let mut session = Session::new(LockedData::new("password").unwrap());
+        session.unlock("password", false).unwrap();
+        insert(&mut session).unwrap();
+        session.lock();
+
+        session.unlock("password", false).unwrap();
+        assert_eq!(None, session.data().unwrap().get("Website"));
+    }
+
+    #[test]
+    fn test_autosave_encrypts_every_change()
+    {
+        let mut session = Session::new(LockedData::new("password").unwrap());
+        session.unlock("password", true).unwrap();
+        insert(&mut session).unwrap();
+        assert_eq!(SessionState::Unlocked, session.state());
+        assert!(session.take_saved());
+        assert!(!session.take_saved());
+
+        session.lock();
+        session.unlock("password", false).unwrap();
+        assert!(session.data().unwrap().get("Website").is_some());
+    }
+
+    #[test]
+    fn test_autosave_after_password_change()
+    {
+        let mut session = Session::new(LockedData::new("password").unwrap());
+        session.unlock("password", true).unwrap();
+        assert!(session.change_password("wrong", "new").is_err());
+        session.change_password("password", "new").unwrap();
+        insert(&mut session).unwrap();
+
+        session.lock();
+        assert!(session.unlock("password", false).is_err());
+        session.unlock("new", false).unwrap();
+        assert!(session.data().unwrap().get("Website").is_some());
+    }
+
+    #[test]
+    fn test_modify_locked_session()
+    {
+        let mut session = Session::new(LockedData::new("password").unwrap());
+        assert!(insert(&mut session).is_err());
+        assert!(session.save("password").is_err());
+    }
+}
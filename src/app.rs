@@ -1,121 +1,657 @@
-use fobword_core::config::{Config, Data, LockedData, SymbolLevel};
+use fobword_core::command::{self, Command, Generate};
+use fobword_core::config::{DataInformation, LockedData, SymbolLevel};
 use fobword_core::converter::{Converter, Key, Modifier};
 use fobword_core::error::DataHandleError;
-use serde::{Deserialize, Serialize};
-use std::fs::OpenOptions;
-use std::io::Write;
+use fobword_core::lockout::Lockout;
+use fobword_core::pace::HostProfile;
+use fobword_core::session::{Session, SessionState};
+use std::path::PathBuf;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
 use SSD1306_Terminal::window::Window;
 
 use super::*;
 
 pub struct App {
     iohelper: IOhelper,
-    data: LockedData,
+    session: Session,
     settings: AppSettings,
+    lockout: Lockout,
+    paths: Paths,
 }
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct AppSettings {
-    pub output: String,
-    pub macro_key: Vec<u8>,
-    pub output_location: String,
-    pub input_location: String,
+
+/// Generated passwords can't be longer than this, typing them takes a while.
+const MAXIMUM_PASSWORD_LENGTH: u8 = 128;
+
+/// The reason the unlocked loop ended.
+enum SessionEnd {
+    Lock,
+    Exit,
 }
 
 impl App {
-    pub fn new(settings: AppSettings, data: LockedData) -> Result<App, DataHandleError> {
-        let data = data;
-        let settings = settings;
-        let mut converter = Converter::from_paths(&settings.input_location, &settings.output_location)?;
-        converter.add_macro(Modifier::from(settings.macro_key[0]), settings.macro_key[2]);
-        let window = Window::new("/dev/i2c-0", 0x3c)?;
-        let iohelper = IOhelper::new(&settings.output, converter, window)?;
+    /// Create the App, running the first run wizard when there is no vault yet.
+    pub fn new(settings: AppSettings, data: Option<LockedData>, paths: Paths) -> Result<App, DataHandleError> {
+        let mut settings = settings;
+        settings.validate()?;
+        let converter = App::converter(&settings, &paths)?;
+        let window = Window::new(&paths.display, 0x3c)?;
+        let gadget = paths.gadget.clone().unwrap_or_else(|| PathBuf::from(&settings.output));
+        let mut iohelper = IOhelper::new(&gadget, settings.report_protocol, settings.input_backend, converter, window)?;
+        iohelper.set_mask(settings.secret_mask);
+        let mut lockout = App::load_lockout(&paths)?;
+        let data = match data {
+            Some(data) => data,
+            None => wizard::run(&mut iohelper, &mut settings, &mut lockout, &paths)?,
+        };
 
         Ok(App {
             iohelper,
-            data,
+            session: Session::new(data),
             settings,
+            lockout,
+            paths,
         })
     }
 
+    /// Load the layouts and macro key from the settings.
+    pub(crate) fn converter(settings: &AppSettings, paths: &Paths) -> Result<Converter, DataHandleError> {
+        // Paths given on the command line are only used for this run, they are not saved in the settings
+        let mut converter = match &paths.layout {
+            Some(layout) => Converter::from_paths(layout, layout)?,
+            None => Converter::from_paths(&settings.input_location, &settings.output_location)?,
+        };
+        converter.add_macro(Modifier::from(settings.macro_key[0]), settings.macro_key[2]);
+        Ok(converter)
+    }
+
     pub fn main_loop(&mut self) -> Result<(), DataHandleError> {
         'outer: loop {
             self.iohelper.screen_off()?;
             self.iohelper.wait_for(Key::Macro)?;
             self.iohelper.screen_on()?;
 
-            self.iohelper.window
-                .print_to_buffer("Please enter device password:")?;
-            let mut data = match self.data.unlock(&self.iohelper.read_line()?) {
-                Ok(value) => value,
-                Err(DataHandleError::ArgonError(password_hash::Error::Password)) => {
-                    println!("Invalid password");
-                    continue;
-                }
+            if !self.unlock()? {
+                break 'outer;
+            }
+            // A duress unlock wiped the real slot, write it out straight away without telling anyone
+            if self.session.locked_data_mut().take_wiped()
+            {
+                self.write_vault()?;
+                self.erase_backups()?;
+            }
+
+            self.iohelper.set_timeout(self.settings.lock_timeout.map(Duration::from_secs));
+            self.iohelper.set_lock_policy(Some(self.settings.lock_on.clone()));
+            let result = self.unlocked_loop();
+            self.iohelper.set_timeout(None);
+            self.iohelper.set_lock_policy(None);
+            // Drop the decrypted data as soon as the device locks, nobody is there to ask about unsaved changes after a timeout or event
+            self.session.lock();
+            match result {
+                Ok(SessionEnd::Exit) => break 'outer,
+                Ok(SessionEnd::Lock) => (),
+                Err(DataHandleError::RecvTimeoutError(RecvTimeoutError::Timeout)) => (),
+                Err(DataHandleError::Locked(_)) => (),
+                Err(DataHandleError::IOError(ref e))
+                    if self.settings.lock_on.host_disconnected && IOhelper::is_host_disconnect(e) => (),
                 Err(error) => return Err(error),
-            };
+            }
+        }
+        Ok(())
+    }
 
-            'inner: loop {
-                let command = self.iohelper.read_line()?;
-                match command.as_ref() {
-                    "" => { self.iohelper.write_key(&Key::Macro)?; },
-                    "lock" => break 'inner,
-                    // Hint - print the hint/comment
-                    "new" => self.action_create_macro(&mut data)?,
-                    "save" => self.action_save_data(&mut data)?,
-                    "exit" => break 'outer,
-                    "change" => self.action_change_password()?,
-                    "gen" | "generate" => self.action_generate_password(&mut data)?,
-                    _ => self.action_use_macro(&data, &command)?,
-                }
-
-                self.iohelper.screen_off()?;
-                self.iohelper.wait_for(Key::Macro)?;
-                self.iohelper.screen_on();
+    /// Handle commands until the device is locked or the program exits.
+    ///
+    /// Returns a `RecvTimeoutError` when there was no input for the lock timeout,
+    /// and `DataHandleError::Locked` when a device or host event locked the device.
+    fn unlocked_loop(&mut self) -> Result<SessionEnd, DataHandleError> {
+        loop {
+            if self.session.state() == SessionState::Dirty {
+                self.iohelper.println("* Unsaved changes")?;
             }
+            let result = match self.iohelper.read_command(Command::complete) {
+                Ok(line) if line.is_empty() => self.iohelper.tap_key(&Key::Macro).map(|_| None).map_err(DataHandleError::from),
+                Ok(line) => Command::parse(&line).and_then(|command| self.run_command(command)),
+                Err(error) => Err(error),
+            };
+            match result {
+                Ok(Some(end)) => return Ok(end),
+                Ok(None) => (),
+                Err(DataHandleError::InvalidCommand(message)) => { self.iohelper.println(&message)?; },
+                // Escape at any prompt drops the command
+                Err(DataHandleError::Cancelled) => { self.iohelper.println("Cancelled")?; },
+                Err(error) => return Err(error),
+            }
+            // Autosave encrypted the change already, it only has to be written
+            if self.session.take_saved() {
+                self.write_vault()?;
+            }
+
             self.iohelper.screen_off()?;
+            self.iohelper.wait_for(Key::Macro)?;
+            self.iohelper.screen_on()?;
+        }
+    }
+
+    /// Run a command, returns how the session ends if the command ends it.
+    fn run_command(&mut self, command: Command) -> Result<Option<SessionEnd>, DataHandleError> {
+        match command {
+            Command::Type(name) => self.action_use_macro(&name, None)?,
+            Command::TypeAt { name, host } => self.action_type_at(name, host)?,
+            Command::New { name } => self.action_create_macro(name)?,
+            Command::Generate(arguments) => self.action_generate_password(arguments)?,
+            Command::Regenerate(arguments) => self.action_regenerate_password(arguments)?,
+            Command::Update { name } => self.action_update_macro(name)?,
+            Command::Delete { name } => self.action_delete_macro(name)?,
+            Command::Hint { name } => self.action_print_hint(name)?,
+            Command::Comment { name } => self.action_print_comment(name)?,
+            Command::Host { name, host } => self.action_set_host(name, host)?,
+            Command::Save => self.action_save_data()?,
+            Command::Change => self.action_change_password()?,
+            Command::Duress => self.action_set_duress_password()?,
+            Command::Help => self.action_help()?,
+            Command::Lock => if self.confirm_unsaved()? { return Ok(Some(SessionEnd::Lock)) },
+            Command::Exit => if self.confirm_unsaved()? { return Ok(Some(SessionEnd::Exit)) },
+            Command::Reset => if self.action_factory_reset()? { return Ok(Some(SessionEnd::Lock)) },
+        }
+        Ok(None)
+    }
+
+    /// Ask for the device password until the data is unlocked.
+    ///
+    /// Returns false when the vault was erased after too many failed attempts.
+    fn unlock(&mut self) -> Result<bool, DataHandleError>
+    {
+        loop
+        {
+            self.wait_for_lockout()?;
+            self.iohelper.println("Please enter device password:")?;
+            let password = match self.iohelper.read_secret() {
+                Err(DataHandleError::Cancelled) => continue,
+                result => result?,
+            };
+
+            // Count the attempt before checking it, so cutting the power during the check does not skip it
+            self.lockout.record_failure();
+            self.save_lockout()?;
+            match self.session.unlock(&password, self.settings.autosave)
+            {
+                Ok(()) =>
+                {
+                    self.lockout.reset();
+                    self.save_lockout()?;
+                    return Ok(true)
+                },
+                Err(DataHandleError::ArgonError(password_hash::Error::Password)) =>
+                {
+                    if self.lockout.should_wipe(self.settings.wipe_after_failed_attempts)
+                    {
+                        self.wipe_vault()?;
+                        return Ok(false)
+                    }
+                    let message = format!("Invalid password, {} failed attempts", self.lockout.failed_attempts);
+                    self.iohelper.println(&message)?;
+                },
+                // The password was right, but the vault it opened can't be decrypted
+                Err(error) if is_damaged(&error) =>
+                {
+                    if !self.restore_backup(&password)?
+                    {
+                        return Err(error)
+                    }
+                    self.lockout.reset();
+                    self.save_lockout()?;
+                    return Ok(true)
+                },
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn wait_for_lockout(&mut self) -> Result<(), DataHandleError>
+    {
+        App::wait_for(&mut self.iohelper, &self.lockout)
+    }
+
+    /// Show the delay of the lockout on the screen and wait for it, ignoring anything typed in the meantime.
+    pub(crate) fn wait_for(iohelper: &mut IOhelper, lockout: &Lockout) -> Result<(), DataHandleError>
+    {
+        let delay = lockout.delay();
+        if delay.as_secs() > 0
+        {
+            iohelper.println(&format!("Wait {} seconds", delay.as_secs()))?;
+            std::thread::sleep(delay);
+            iohelper.discard_input();
+        }
+        Ok(())
+    }
+
+    /// Erase the vault file, its back-ups and `import.yaml` after too many failed attempts.
+    fn wipe_vault(&mut self) -> Result<(), DataHandleError>
+    {
+        fileutilities::secure_erase(&self.paths.vault)?;
+        self.erase_backups()?;
+        self.lockout.reset();
+        self.save_lockout()?;
+        self.iohelper.println("Too many attempts, vault erased")?;
+        Ok(())
+    }
+
+    fn load_lockout(paths: &Paths) -> Result<Lockout, DataHandleError>
+    {
+        match std::fs::read_to_string(&paths.lockout)
+        {
+            Ok(buffer) => Lockout::from_yaml(&buffer),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Lockout::new()),
+            Err(e) => Err(DataHandleError::IOError(e)),
+        }
+    }
+
+    fn save_lockout(&mut self) -> Result<(), DataHandleError>
+    {
+        App::write_lockout(&self.paths, &self.lockout)
+    }
+
+    /// Write the failed attempts to disk, so they survive reboots.
+    pub(crate) fn write_lockout(paths: &Paths, lockout: &Lockout) -> Result<(), DataHandleError>
+    {
+        fileutilities::atomic_write(&paths.lockout, lockout.to_yaml()?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Ask to save or discard unsaved changes before the data is dropped.
+    ///
+    /// Returns false when the user went back to the unlocked device instead.
+    fn confirm_unsaved(&mut self) -> Result<bool, DataHandleError>
+    {
+        if self.session.state() != SessionState::Dirty
+        {
+            return Ok(true)
+        }
+        loop
+        {
+            self.iohelper.println("Unsaved changes, save? (y/n/cancel)")?;
+            match self.iohelper.read_line()?.as_ref()
+            {
+                "y" =>
+                {
+                    self.iohelper.println("Type master password:")?;
+                    return self.action_save_data().map(|_| self.session.state() != SessionState::Dirty)
+                },
+                "n" => return Ok(true),
+                "cancel" => return Ok(false),
+                _ => (),
+            }
         }
+    }
+
+    /// List the commands and their arguments.
+    fn action_help(&mut self) -> Result<(), DataHandleError>
+    {
+        self.iohelper.clear_screen();
+        self.iohelper.println(&format!("Commands start with '{}':", command::PREFIX))?;
+        for (_, usage) in command::COMMANDS.iter() {
+            self.iohelper.println(usage)?;
+        }
+        Ok(())
+    }
+
+    /// Generate a password, arguments left out on the command line are asked for.
+    ///
+    /// The comment is only asked for when the command had no arguments.
+    fn action_generate_password(&mut self, arguments: Generate) -> Result<(), DataHandleError> 
+    {
+        let ask_comment = arguments == Generate::default();
+        let name = match self.read_new_name(arguments.name)? {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        let (length, symbols) = match self.read_constraints(arguments.length, arguments.symbols)? {
+            Some(constraints) => constraints,
+            None => return Ok(()),
+        };
+        let comment = match arguments.comment {
+            Some(comment) => Some(comment),
+            None if ask_comment => self.read_optional("Comment (optional):")?,
+            None => None,
+        };
+        self.session.modify(|data| data.generate(name, comment, length, symbols))??;
+        self.iohelper.println("Password generated")?;
         Ok(())
     }
 
-    fn action_generate_password(&mut self, data: &mut Data) -> Result<(), DataHandleError> 
+    /// Replace the password of an existing macro with a new generated one, an empty comment keeps the old comment.
+    fn action_regenerate_password(&mut self, arguments: Generate) -> Result<(), DataHandleError>
     {
-        self.iohelper.println("Enter macro name:")?;
-        let name = self.iohelper.read_line()?;
-        data.generate(name, None, 15, SymbolLevel::Symbols)
+        let ask_comment = arguments == Generate::default();
+        let (name, information) = match self.read_existing_name(arguments.name)? {
+            Some(existing) => existing,
+            None => return Ok(()),
+        };
+        let (length, symbols) = match self.read_constraints(arguments.length, arguments.symbols)? {
+            Some(constraints) => constraints,
+            None => return Ok(()),
+        };
+        let comment = match arguments.comment {
+            Some(comment) => Some(comment),
+            None if ask_comment => self.read_optional("Comment (optional):")?,
+            None => None,
+        };
+        let comment = comment.or(information.comment);
+        self.session.modify(|data| data.generate(name, comment, length, symbols))??;
+        self.iohelper.println("Password regenerated")?;
+        Ok(())
     }
 
     fn action_change_password(&mut self) -> Result<(), DataHandleError> 
     {
         self.iohelper.clear_screen();
         self.iohelper.println("Type old password:")?;
-        let old_password = self.iohelper.read_line()?;
+        let old_password = self.iohelper.read_secret()?;
 
         self.iohelper.println("Type new password:")?;
-        let new_password = self.iohelper.read_line()?;
+        let new_password = self.iohelper.read_secret()?;
 
         self.iohelper.println("Confirm password:")?;
-        if self.iohelper.read_line()? == new_password {
-            return self.data.change_password(&old_password, &new_password);
+        if self.iohelper.read_secret()? != new_password {
+            self.iohelper.println("The passwords do not match")?;
+            return Ok(());
+        }
+        match self.session.change_password(&old_password, &new_password)
+        {
+            Ok(()) =>
+            {
+                self.write_vault()?;
+                self.iohelper.println("Password changed")?;
+                Ok(())
+            },
+            Err(DataHandleError::ArgonError(password_hash::Error::Password)) =>
+            {
+                self.iohelper.println("Invalid password")?;
+                Ok(())
+            },
+            Err(DataHandleError::LockedData(message)) => { self.iohelper.println(&message)?; Ok(()) },
+            Err(error) => Err(error),
         }
-        self.iohelper.println("The passwords do not match")?;
+    }
+
+    fn action_set_duress_password(&mut self) -> Result<(), DataHandleError>
+    {
+        self.iohelper.clear_screen();
+        self.iohelper.println("Type master password:")?;
+        let password = self.iohelper.read_secret()?;
+
+        self.iohelper.println("Type duress password:")?;
+        let duress_password = self.iohelper.read_secret()?;
+
+        self.iohelper.println("Confirm password:")?;
+        if self.iohelper.read_secret()? != duress_password
+        {
+            self.iohelper.println("The passwords do not match")?;
+            return Ok(());
+        }
+
+        self.iohelper.println("Wipe vault on use? (y/n)")?;
+        let wipe = self.iohelper.read_line()? == "y";
+        match self.session.locked_data_mut().set_duress_password(&password, &duress_password, wipe)
+        {
+            Ok(()) => self.write_vault(),
+            Err(DataHandleError::ArgonError(password_hash::Error::Password)) =>
+            {
+                self.iohelper.println("Invalid password")?;
+                Ok(())
+            },
+            Err(DataHandleError::LockedData(message)) => { self.iohelper.println(&message)?; Ok(()) },
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Erase the vault, its back-ups and the settings, then run the first run wizard again.
+    ///
+    /// Returns true when the device was reset, the unlocked data belongs to the erased vault then.
+    fn action_factory_reset(&mut self) -> Result<bool, DataHandleError>
+    {
+        self.iohelper.clear_screen();
+        self.iohelper.println("Type master password:")?;
+        let password = self.iohelper.read_secret()?;
+        match self.session.check_master_password(&password)
+        {
+            Ok(()) => (),
+            // The duress password is refused the same way, the device must not be erased under duress
+            Err(DataHandleError::ArgonError(password_hash::Error::Password)) | Err(DataHandleError::LockedData(_)) =>
+            {
+                self.iohelper.println("Invalid password")?;
+                return Ok(false)
+            },
+            Err(error) => return Err(error),
+        }
+
+        self.iohelper.println("Everything will be erased, type RESET to continue:")?;
+        if self.iohelper.read_line()? != "RESET"
+        {
+            self.iohelper.println("Reset cancelled")?;
+            return Ok(false)
+        }
+
+        fileutilities::secure_erase(&self.paths.vault)?;
+        self.erase_backups()?;
+        fileutilities::secure_erase(&self.paths.config)?;
+        fileutilities::secure_erase(&self.paths.lockout)?;
+        self.lockout = Lockout::new();
+        self.iohelper.println("Device erased")?;
+
+        // The wizard waits for the user, it can't be stopped by the lock timeout or events
+        self.iohelper.set_timeout(None);
+        self.iohelper.set_lock_policy(None);
+        self.settings = AppSettings::new(&self.paths);
+        self.iohelper.converter = App::converter(&self.settings, &self.paths)?;
+        self.iohelper.set_mask(self.settings.secret_mask);
+        self.session = Session::new(wizard::run(&mut self.iohelper, &mut self.settings, &mut self.lockout, &self.paths)?);
+        Ok(true)
+    }
+
+    fn action_create_macro(&mut self, name: Option<String>) -> Result<(), DataHandleError> {
+        let name = match self.read_new_name(name)? {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        let pass = match self.read_password()? {
+            Some(pass) => pass,
+            None => return Ok(()),
+        };
+        let hint = self.read_optional("Hint (optional):")?;
+        let comment = self.read_optional("Comment (optional):")?;
+        self.session.modify(|data| data.insert(name, DataInformation::new(hint, comment, pass)))?;
+        self.iohelper.println("Macro created")?;
         Ok(())
     }
 
-    fn action_create_macro(&mut self, data: &mut Data) -> Result<(), DataHandleError> {
-        let name = self.iohelper.read_line()?;
-        let pass = self.iohelper.read_line()?;
-        data.insert(name, DataInformation::new(None, None, pass));
+    /// Change the password and hint of a macro, an empty hint keeps the old hint.
+    fn action_update_macro(&mut self, name: Option<String>) -> Result<(), DataHandleError> {
+        let (name, information) = match self.read_existing_name(name)? {
+            Some(existing) => existing,
+            None => return Ok(()),
+        };
+        let pass = match self.read_password()? {
+            Some(pass) => pass,
+            None => return Ok(()),
+        };
+        let hint = self.read_optional("Hint (optional):")?.or(information.hint);
+        let mut updated = DataInformation::new(hint, information.comment, pass);
+        updated.host = information.host;
+        self.session.modify(|data| data.insert(name, updated))?;
+        self.iohelper.println("Macro updated")?;
         Ok(())
     }
 
-    fn action_use_macro(&mut self, data: &Data, command: &str) -> Result<(), DataHandleError> {
-        if let Some(information) = data.get(command) 
+    /// Delete a macro after its name was typed a second time.
+    fn action_delete_macro(&mut self, name: Option<String>) -> Result<(), DataHandleError> {
+        let (name, _) = match self.read_existing_name(name)? {
+            Some(existing) => existing,
+            None => return Ok(()),
+        };
+        self.iohelper.println("Type the name again to delete:")?;
+        if self.iohelper.read_line()? != name {
+            self.iohelper.println("Names do not match, not deleted")?;
+            return Ok(());
+        }
+        self.session.modify(|data| data.remove(&name))?;
+        self.iohelper.println("Macro deleted")?;
+        Ok(())
+    }
+
+    fn action_type_at(&mut self, name: Option<String>, host: Option<HostProfile>) -> Result<(), DataHandleError> {
+        if let Some((name, _)) = self.read_existing_name(name)? {
+            self.action_use_macro(&name, host)?;
+        }
+        Ok(())
+    }
+
+    /// Set the host profile a macro is typed at, asking for it when it was not given.
+    fn action_set_host(&mut self, name: Option<String>, host: Option<HostProfile>) -> Result<(), DataHandleError> {
+        let (name, mut information) = match self.read_existing_name(name)? {
+            Some(existing) => existing,
+            None => return Ok(()),
+        };
+        let host = match host {
+            Some(host) => Some(host),
+            None => {
+                self.iohelper.println("Host: 1 default, 2 slow-vm, 3 bios")?;
+                HostProfile::from_name(&self.iohelper.read_line()?)
+            }
+        };
+        let host = match host {
+            Some(host) => host,
+            None => {
+                self.iohelper.println("Type 1 to 3")?;
+                return Ok(());
+            }
+        };
+        information.host = Some(host).filter(|host| *host != HostProfile::Default);
+        self.session.modify(|data| data.insert(name, information))?;
+        self.iohelper.println(&format!("Typed at the {} pace", host.name()))?;
+        Ok(())
+    }
+
+    fn action_print_hint(&mut self, name: Option<String>) -> Result<(), DataHandleError> {
+        if let Some((_, information)) = self.read_existing_name(name)? {
+            let hint = information.hint.unwrap_or_else(|| "No hint".to_owned());
+            self.iohelper.println(&hint)?;
+        }
+        Ok(())
+    }
+
+    fn action_print_comment(&mut self, name: Option<String>) -> Result<(), DataHandleError> {
+        if let Some((_, information)) = self.read_existing_name(name)? {
+            let comment = information.comment.unwrap_or_else(|| "No comment".to_owned());
+            self.iohelper.println(&comment)?;
+        }
+        Ok(())
+    }
+
+    /// Ask for a name unless it was given with the command.
+    fn read_name(&mut self, name: Option<String>) -> Result<String, DataHandleError> {
+        match name {
+            Some(name) => Ok(name),
+            None => {
+                self.iohelper.println("Enter macro name:")?;
+                self.iohelper.read_line()
+            }
+        }
+    }
+
+    /// Read the name for a new macro, `None` if it is empty or already taken.
+    fn read_new_name(&mut self, name: Option<String>) -> Result<Option<String>, DataHandleError> {
+        let name = self.read_name(name)?;
+        let message = if name.trim().is_empty() {
+            "Name can not be empty"
+        } else if self.session.data().is_some_and(|data| data.get(&name).is_some()) {
+            "Macro exists, use update"
+        } else {
+            return Ok(Some(name));
+        };
+        self.iohelper.println(message)?;
+        Ok(None)
+    }
+
+    /// Read the name of an existing macro, `None` after telling the user there is no such macro.
+    fn read_existing_name(&mut self, name: Option<String>) -> Result<Option<(String, DataInformation)>, DataHandleError> {
+        let name = self.read_name(name)?;
+        match self.session.data().and_then(|data| data.get(&name)).cloned() {
+            Some(information) => Ok(Some((name, information))),
+            None => {
+                self.iohelper.println("No macro found with that name.")?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Read a password for a macro, `None` if it is empty or can't be typed with the output layout.
+    fn read_password(&mut self) -> Result<Option<String>, DataHandleError> {
+        self.iohelper.println("Enter password:")?;
+        let pass = self.iohelper.read_secret()?;
+        if pass.is_empty() {
+            self.iohelper.println("Password can not be empty")?;
+            return Ok(None);
+        }
+        if converterutilities::string_to_report_buffers(&self.iohelper.converter, self.iohelper.protocol(), &pass).is_none() {
+            self.iohelper.println("The layout can't type that password")?;
+            return Ok(None);
+        }
+        Ok(Some(pass))
+    }
+
+    /// Read the length and symbol level of a generated password, unless they were given with the command.
+    fn read_constraints(&mut self, length: Option<u8>, symbols: Option<SymbolLevel>) -> Result<Option<(u8, SymbolLevel)>, DataHandleError> {
+        let length = match length {
+            Some(length) => Ok(length),
+            None => {
+                self.iohelper.println(&format!("Password length (1-{}):", MAXIMUM_PASSWORD_LENGTH))?;
+                self.iohelper.read_line()?.trim().parse::<u8>()
+            }
+        };
+        let length = match length {
+            Ok(length) if (1..=MAXIMUM_PASSWORD_LENGTH).contains(&length) => length,
+            _ => {
+                self.iohelper.println(&format!("Length must be 1 to {}", MAXIMUM_PASSWORD_LENGTH))?;
+                return Ok(None);
+            }
+        };
+        let symbols = match symbols {
+            Some(symbols) => Some(symbols),
+            None => {
+                self.iohelper.println("Symbols: 1 lower, 2 upper, 3 numbers, 4 symbols")?;
+                SymbolLevel::from_name(&self.iohelper.read_line()?)
+            }
+        };
+        match symbols {
+            Some(symbols) => Ok(Some((length, symbols))),
+            None => {
+                self.iohelper.println("Type 1 to 4")?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Read an optional text, `None` when it was left empty.
+    fn read_optional(&mut self, prompt: &str) -> Result<Option<String>, DataHandleError> {
+        self.iohelper.println(prompt)?;
+        let text = self.iohelper.read_line()?;
+        Ok(if text.is_empty() { None } else { Some(text) })
+    }
+
+    /// Type the password of a macro, at the pace of `host` or else the host profile stored with the macro.
+    fn action_use_macro(&mut self, command: &str, host: Option<HostProfile>) -> Result<(), DataHandleError> {
+        if let Some(information) = self.session.data().and_then(|data| data.get(command)) 
         {
-            let buffers =
-                converterutilities::string_to_report_buffers(&self.iohelper.converter, &information.blob);
-            if let Some(buffers) = buffers 
+            let pace = self.settings.host_profiles.pace(host.or(information.host).unwrap_or_default());
+            // The reports are made while they are written, the converter and the gadget are borrowed separately
+            let reports =
+                converterutilities::string_to_report_buffers(&self.iohelper.converter, self.iohelper.protocol(), &information.blob);
+            if let Some(reports) = reports 
             {
-                self.iohelper.write_buffers_to_file(buffers)?;
+                self.iohelper.output.write_paced(reports.one_key_per_report(pace.one_key_per_report()), pace)?;
             }
         } 
         else 
@@ -125,23 +661,66 @@ impl App {
         Ok(())
     }
 
-    fn action_save_data(&mut self, data: &mut Data) -> Result<(), DataHandleError> 
+    fn action_save_data(&mut self) -> Result<(), DataHandleError> 
     {
-        if std::path::Path::new("/usr/bin/config.yaml").exists()
+        let password = self.iohelper.read_secret()?;
+        match self.session.save(&password)
         {
-            std::fs::copy("/usr/bin/config.yaml", "/usr/bin/back-up-config.yaml")?;
-        }
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open("/usr/bin/config.yaml")?;
-        let password = self.iohelper.read_line()?;
-        self.data.lock(&password, data.clone())?;
-        let config = Config::new(Some(self.settings.clone()), Some(self.data.clone()));
-        let buffer = config.to_yaml()?;
-
-        file.write_all(buffer.as_bytes())?;
+            Ok(()) =>
+            {
+                self.write_vault()?;
+                self.iohelper.println("Saved")?;
+                Ok(())
+            },
+            Err(DataHandleError::ArgonError(password_hash::Error::Password)) =>
+            {
+                self.iohelper.println("Invalid password, not saved")?;
+                Ok(())
+            },
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Write the locked data to the vault file, keeping the previous files as back-ups.
+    fn write_vault(&mut self) -> Result<(), DataHandleError>
+    {
+        let buffer = self.session.locked_data().to_yaml()?;
+
+        fileutilities::rotate_backups(&self.paths.vault, self.settings.backup_generations)?;
+        fileutilities::atomic_write(&self.paths.vault, buffer.as_bytes())?;
+        Ok(())
+    }
+
+    /// Unlock the newest back-up of a damaged vault the password opens, and write it over the vault.
+    ///
+    /// Returns false when no back-up could be unlocked.
+    fn restore_backup(&mut self, password: &str) -> Result<bool, DataHandleError>
+    {
+        for backup in fileutilities::backups(&self.paths.vault)?
+        {
+            let mut session = match read_with(&backup, &LockedData::from_yaml) {
+                Ok(data) => Session::new(data),
+                Err(_) => continue,
+            };
+            if session.unlock(password, self.settings.autosave).is_ok()
+            {
+                self.iohelper.println("Vault damaged, using a back-up")?;
+                // Written without rotating, so the damaged vault does not push out a good back-up
+                fileutilities::atomic_write(&self.paths.vault, session.locked_data().to_yaml()?.as_bytes())?;
+                self.session = session;
+                return Ok(true)
+            }
+        }
+        Ok(false)
+    }
+
+    /// Erase every back-up of the vault, and the import it may have been made from.
+    fn erase_backups(&mut self) -> Result<(), DataHandleError>
+    {
+        for backup in self.paths.vault_backups()?
+        {
+            fileutilities::secure_erase(backup)?;
+        }
         Ok(())
     }
 }
@@ -1,89 +1,184 @@
-use fobword_core::converter::{Converter, Key, Modifier};
+use fobword_core::converter::{Converter, Key};
+use fobword_core::report::{KeyboardReport, ReportProtocol};
 
 
-/// Convert a string to raw input buffers.
+/// Convert a string to keyboard reports.
 ///
 /// This is a convience function to convert a String into the least ammount of raw input reports.
+/// Names of keys in braces, like `{Tab}` or `{Enter}`, type that key, see [`Key::sequence`].
+/// Returns `None` when the string is empty or has a character the layout can't type.
+/// The reports are made while they are read, nothing is allocated.
 ///
 /// # Example
 /// ```
 /// let conv = Converter::default();
 /// let word = "test";
 /// 
-/// let buffers = converterutilities::string_to_report_buffers(&conv, &word).unwrap();
+/// let reports: Vec<KeyboardReport> = converterutilities::string_to_report_buffers(&conv, ReportProtocol::Boot, &word).unwrap().collect();
 ///
-/// let expected = vec![
-///     vec![0, 0, 0x17, 0x08, 0x16, 0, 0, 0], 
-///     vec![0, 0, 0, 0, 0, 0, 0, 0,],
-///     vec![0, 0, 0x17, 0, 0, 0, 0, 0],
-///     vec![0, 0, 0, 0, 0, 0, 0, 0,]];
+/// let expected: [&[u8]; 4] = [
+///     &[0, 0, 0x17, 0x08, 0x16, 0, 0, 0], 
+///     &[0, 0, 0, 0, 0, 0, 0, 0,],
+///     &[0, 0, 0x17, 0, 0, 0, 0, 0],
+///     &[0, 0, 0, 0, 0, 0, 0, 0,]];
 /// 
-/// assert_eq!(expected, buffers);
+/// assert_eq!(expected.to_vec(), reports.iter().map(KeyboardReport::as_bytes).collect::<Vec<_>>());
 /// ```
-pub fn string_to_report_buffers(conv: &Converter, word: &str) -> Option<Vec<Vec<u8>>>
+pub fn string_to_report_buffers<'a>(conv: &'a Converter, protocol: ReportProtocol, word: &'a str) -> Option<ReportStream<'a, impl Iterator<Item = Key> + Clone + 'a>>
 {
-    // vector of buffers that are to be send
-    let mut completed_report_buffers = Vec::new();
-    // current buffer we are writing to
-    let mut in_process_buffer = vec![0u8;8];
-    // index of the current buffer we are writing too
-    let mut index = 0;
+    keys_to_report_buffers(conv, protocol, Key::sequence(word))
+}
 
-    let mut chars = word.chars();
-    if let Some(c) = chars.next()
-    {   
-        let (char_shift_code, char_code) = conv.get_raw(&Key::Char(c));
-        write_first_char_to_buffer(&mut index, &mut in_process_buffer, char_code, char_shift_code);
-    }
-    else
+/// Convert a sequence of keys to keyboard reports, like [`string_to_report_buffers`] but with the keys already read.
+///
+/// Returns `None` when there are no keys or the layout can't type one of them.
+///
+/// # Example
+/// ```
+/// let conv = Converter::default();
+/// let keys = vec![Key::Char('a'), Key::Char('\t'), Key::Char('b'), Key::Enter];
+///
+/// let mut reports = converterutilities::keys_to_report_buffers(&conv, ReportProtocol::Boot, keys).unwrap();
+///
+/// assert_eq!(&[0, 0, 0x04, 0x2b, 0x05, 0x28, 0, 0], reports.next().unwrap().as_bytes());
+/// ```
+pub fn keys_to_report_buffers<I>(conv: &Converter, protocol: ReportProtocol, keys: I) -> Option<ReportStream<'_, I::IntoIter>>
+where
+    I: IntoIterator<Item = Key>,
+    I::IntoIter: Clone,
+{
+    let keys = keys.into_iter();
+    let mut check = keys.clone().peekable();
+    if check.peek().is_none() || check.any(|key| conv.get_raw(&key).1 == 0)
     {
         return None
-    }        
-    
-    for c in chars
+    }
+    let report = KeyboardReport::new(protocol);
+    Some(ReportStream { conv, keys, in_process: report, previous: report, pending: None, one_key_per_report: false })
+}
+
+/// The reports that type a sequence of keys, see [`keys_to_report_buffers`].
+///
+/// Ends with a report where every key is released.
+pub struct ReportStream<'a, I>
+{
+    conv: &'a Converter,
+    keys: I,
+    // current report we are writing to
+    in_process: KeyboardReport,
+    // the report sent last
+    previous: KeyboardReport,
+    // a report to send before going on with the keys
+    pending: Option<KeyboardReport>,
+    // press and release every key on its own
+    one_key_per_report: bool,
+}
+
+impl<'a, I> ReportStream<'a, I>
+{
+    /// Press and release every key on its own instead of pressing several at once, for hosts that miss keys.
+    pub fn one_key_per_report(mut self, one_key_per_report: bool) -> ReportStream<'a, I>
     {
-        let (char_shift_code, char_code) = conv.get_raw(&Key::Char(c));
-        if in_process_buffer.contains(&char_code)
-        {
-            completed_report_buffers.push(in_process_buffer);
-            completed_report_buffers.push(vec![0u8;8]);
-            in_process_buffer = vec![0u8;8];
+        self.one_key_per_report = one_key_per_report;
+        self
+    }
+}
 
-            // The first char decides the "Shift" marker.
-            write_first_char_to_buffer(&mut index, &mut in_process_buffer, char_code, char_shift_code);
-            continue;
+impl<'a, I> Iterator for ReportStream<'a, I>
+where I: Iterator<Item = Key>
+{
+    type Item = KeyboardReport;
+
+    fn next(&mut self) -> Option<KeyboardReport>
+    {
+        if let Some(report) = self.pending.take()
+        {
+            return Some(report)
+        }
+        let released = KeyboardReport::new(self.in_process.protocol());
+        for key in self.keys.by_ref()
+        {
+            let (char_shift_code, char_code) = self.conv.get_raw(&key);
+            let completed = self.in_process;
+            if completed.holds(char_code) || (self.one_key_per_report && !completed.is_empty())
+            {
+                // The key has to be released before it is pressed again
+                self.pending = Some(released);
+                self.previous = released;
+            }
+            // This checks if the old report holds the key, if it does it needs to have a report in between where the key is not pressed
+            // If the report is full it needs to be sent, a boot report has room for 6 keys
+            // And the last check is to see if the Shift marker is the same, else we need to send it and make a new report
+            else if self.previous.holds(char_code) ||
+                    completed.is_full() ||
+                    (!completed.is_empty() && completed.modifiers() != char_shift_code.bits())
+            {
+                self.previous = completed;
+            }
+            else
+            {
+                // The key fits, the first char of a report decides the "Shift" marker.
+                self.in_process.set_modifiers(char_shift_code.bits());
+                self.in_process.press(char_code);
+                continue;
+            }
+            self.in_process = released;
+            self.in_process.set_modifiers(char_shift_code.bits());
+            self.in_process.press(char_code);
+            return Some(completed)
         }
-        // This checks if the old buffer contains a character, if it does it needs to have a report in between where the key is not pressed
-        // If the index is the same as or higher than 6, we need to send the buffer since there can be only 6 character at a time
-        // And the last check is to see if the Shift marker is the same, else we need to send it and make a new buffer
-        else if completed_report_buffers.last().unwrap_or(&vec![0u8;8]).contains(&char_code) || 
-                index >= 6 || 
-                &in_process_buffer[0] != &(char_shift_code as u8)
+        if self.in_process.is_empty()
         {
-            completed_report_buffers.push(in_process_buffer);
-            in_process_buffer = vec![0u8;8];
-            write_first_char_to_buffer(&mut index, &mut in_process_buffer, char_code, char_shift_code);
-            continue;
-        };
-        write_to_buffer(&mut index, &mut in_process_buffer, char_code);
+            return None
+        }
+        // Send the last report and an empty one to indicate all keys are released
+        let completed = self.in_process;
+        self.in_process = released;
+        self.pending = Some(released);
+        Some(completed)
     }
-    // Send the last buffer and an empty one to indicate all keys are released
-    completed_report_buffers.push(in_process_buffer);
-    completed_report_buffers.push(vec![0u8;8]);
-    Some(completed_report_buffers)
 }
 
-/// Resets the index, and set the Modifier bit
-fn write_first_char_to_buffer<'a>(index: &mut usize, buffer: &mut Vec<u8>, char_code: u8, modifier: Modifier)
+#[cfg(test)]
+mod converterutilitiestests
 {
-    *index = 0;
-    buffer[0] = modifier as u8;
-    write_to_buffer(index, buffer, char_code);
-}
+    use super::*;
 
-/// Write character to buffer and increment index
-fn write_to_buffer<'a>(index: &mut usize, buffer: &mut Vec<u8>, char_code: u8)
-{
-    buffer[*index + 2] = char_code;
-    *index += 1;
+    fn reports(word: &str) -> Vec<Vec<u8>>
+    {
+        let conv = Converter::default();
+        string_to_report_buffers(&conv, ReportProtocol::Boot, word).unwrap().map(|report| report.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_sequence_types_named_keys()
+    {
+        let expected = vec![vec![0, 0, 0x04, 0x05, 0x2b, 0x06, 0x28, 0], vec![0; 8]];
+        assert_eq!(expected, reports("ab{Tab}c{Enter}"));
+
+        let expected = vec![vec![0, 0, 0x4a, 0x68, 0x5d, 0, 0, 0], vec![0; 8]];
+        assert_eq!(expected, reports("{Home}{F13}{Keypad5}"));
+    }
+
+    #[test]
+    fn test_braces_without_a_key_name_are_typed()
+    {
+        let expected = vec![
+            vec![0, 0, 0x04, 0, 0, 0, 0, 0],
+            vec![0x02, 0, 0x2f, 0, 0, 0, 0, 0],
+            vec![0, 0, 0x05, 0, 0, 0, 0, 0],
+            vec![0x02, 0, 0x30, 0, 0, 0, 0, 0],
+            vec![0; 8],
+        ];
+        assert_eq!(expected, reports("a{b}"));
+    }
+
+    #[test]
+    fn test_untypeable_sequence()
+    {
+        let conv = Converter::default();
+        assert!(string_to_report_buffers(&conv, ReportProtocol::Boot, "").is_none());
+        assert!(string_to_report_buffers(&conv, ReportProtocol::Boot, "a💖").is_none());
+        assert!(keys_to_report_buffers(&conv, ReportProtocol::Boot, vec![Key::Macro]).is_none());
+    }
 }
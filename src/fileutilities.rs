@@ -0,0 +1,220 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// Write the contents to a temporary file next to `path`, sync it to disk and rename it over `path`.
+///
+/// The directory is synced after the rename, so after a crash or power cut `path` holds either the old or the new contents.
+pub fn atomic_write<P: AsRef<Path>>(path: P, contents: &[u8]) -> io::Result<()>
+{
+    let path = path.as_ref();
+    let temporary = with_suffix(path, ".tmp");
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    std::fs::rename(&temporary, path)?;
+    sync_directory(path)
+}
+
+/// Copy the file at `path` to a new backup and erase all but the newest `generations` backups.
+///
+/// Backups are named `<file name>.<generation>-<timestamp>.bak`.
+/// Boards without a real time clock start at 1970 after every boot, so backups are ordered by generation
+/// and the timestamp is only there for people reading the directory.
+pub fn rotate_backups<P: AsRef<Path>>(path: P, generations: usize) -> io::Result<()>
+{
+    let path = path.as_ref();
+    let backups = numbered_backups(path)?;
+    let mut keep = generations;
+    if generations > 0 && path.exists()
+    {
+        let generation = backups.first().map_or(1, |(generation, _)| generation + 1);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let backup = with_suffix(path, &format!(".{}-{}.bak", generation, timestamp));
+        atomic_write(&backup, &std::fs::read(path)?)?;
+        keep -= 1;
+    }
+    for (_, old_backup) in backups.iter().skip(keep)
+    {
+        secure_erase(old_backup)?;
+    }
+    Ok(())
+}
+
+/// List the backups of the file at `path`, newest first.
+pub fn backups<P: AsRef<Path>>(path: P) -> io::Result<Vec<PathBuf>>
+{
+    Ok(numbered_backups(path.as_ref())?.into_iter().map(|(_, backup)| backup).collect())
+}
+
+/// List the backups of the file at `path` with their generation, newest first.
+fn numbered_backups(path: &Path) -> io::Result<Vec<(u64, PathBuf)>>
+{
+    let prefix = match path.file_name().and_then(|name| name.to_str())
+    {
+        Some(name) => format!("{}.", name),
+        None => return Ok(Vec::new()),
+    };
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(directory(path))?
+    {
+        let entry_path = entry?.path();
+        let generation = entry_path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(&prefix))
+            .and_then(|name| name.strip_suffix(".bak"))
+            .and_then(|name| name.split('-').next())
+            .and_then(|generation| generation.parse::<u64>().ok());
+        if let Some(generation) = generation
+        {
+            backups.push((generation, entry_path));
+        }
+    }
+    backups.sort_by_key(|b| std::cmp::Reverse(b.0));
+    Ok(backups)
+}
+
+/// Overwrite a file with random bytes, sync it to disk and remove it.
+///
+/// A file that does not exist is already erased, so this is not an error.
+pub fn secure_erase<P: AsRef<Path>>(path: P) -> io::Result<()>
+{
+    let path = path.as_ref();
+    let mut file = match OpenOptions::new().write(true).open(path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let length = file.metadata()?.len();
+
+    let mut random = OpenOptions::new().read(true).open("/dev/urandom")?.take(length);
+    io::copy(&mut random, &mut file)?;
+    file.flush()?;
+    file.sync_all()?;
+
+    std::fs::remove_file(path)?;
+    sync_directory(path)
+}
+
+/// Sync the directory holding `path`, so renames and removals in it survive a power cut.
+fn sync_directory(path: &Path) -> io::Result<()>
+{
+    File::open(directory(path))?.sync_all()
+}
+
+/// The directory holding `path`, `.` for a bare file name.
+pub fn directory(path: &Path) -> &Path
+{
+    match path.parent()
+    {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf
+{
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod fileutilitiestests
+{
+    use super::*;
+
+    /// An empty directory for one test.
+    fn temp_directory(name: &str) -> PathBuf
+    {
+        let directory = std::env::temp_dir().join(format!("fobword-fileutilities-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_atomic_write()
+    {
+        let directory = temp_directory("atomic");
+        let path = directory.join("vault.yaml");
+        atomic_write(&path, b"old").unwrap();
+        atomic_write(&path, b"new").unwrap();
+
+        assert_eq!(b"new".to_vec(), std::fs::read(&path).unwrap());
+        assert!(!with_suffix(&path, ".tmp").exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_rotate_backups_keeps_generations()
+    {
+        let directory = temp_directory("rotate");
+        let path = directory.join("vault.yaml");
+        for generation in 1..=12
+        {
+            std::fs::write(&path, generation.to_string()).unwrap();
+            rotate_backups(&path, 3).unwrap();
+        }
+
+        // Generation 12 is newer than 9 even though it sorts before it as text
+        let backups = backups(&path).unwrap();
+        assert_eq!(3, backups.len());
+        assert!(backups[0].to_string_lossy().contains("vault.yaml.12-"));
+        let contents: Vec<String> = backups.iter().map(|backup| std::fs::read_to_string(backup).unwrap()).collect();
+        assert_eq!(vec!["12", "11", "10"], contents);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_rotate_backups_without_generations_erases_them()
+    {
+        let directory = temp_directory("prune");
+        let path = directory.join("vault.yaml");
+        std::fs::write(&path, "vault").unwrap();
+        rotate_backups(&path, 2).unwrap();
+        rotate_backups(&path, 2).unwrap();
+        assert_eq!(2, backups(&path).unwrap().len());
+
+        rotate_backups(&path, 0).unwrap();
+        assert!(backups(&path).unwrap().is_empty());
+        assert!(path.exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_backups_skip_other_files()
+    {
+        let directory = temp_directory("backups");
+        let path = directory.join("vault.yaml");
+        std::fs::write(directory.join("vault.yaml.1-0.bak"), "").unwrap();
+        std::fs::write(directory.join("vault.yaml.x-0.bak"), "").unwrap();
+        std::fs::write(directory.join("config.yaml.2-0.bak"), "").unwrap();
+        std::fs::write(directory.join("vault.yaml.tmp"), "").unwrap();
+
+        assert_eq!(vec![directory.join("vault.yaml.1-0.bak")], backups(&path).unwrap());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_secure_erase()
+    {
+        let directory = temp_directory("erase");
+        let path = directory.join("vault.yaml");
+        std::fs::write(&path, "secret").unwrap();
+
+        secure_erase(&path).unwrap();
+        assert!(!path.exists());
+        // Erasing a file that is gone is not an error
+        secure_erase(&path).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
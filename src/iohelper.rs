@@ -1,56 +1,83 @@
-use std::collections::HashMap;
-use std::fs::{OpenOptions, File};
-use std::io::{Read, Write, self};
-use std::path::PathBuf;
+use std::io;
+use std::path::Path;
 use SSD1306_Terminal::window::Window;
 use fobword_core::converter::{Converter, Key};
 use fobword_core::error::DataHandleError;
-use std::sync::mpsc::{self, SendError, Sender, Receiver};
+use fobword_core::input::{Events, InputEvent};
+use fobword_core::lineeditor::{Edit, LineEditor, Mask};
+use fobword_core::report::{KeyboardReport, ReportBuilder, ReportProtocol};
+use crate::reactor::{InputBackend, Reactor, ReactorHandle};
+use crate::reportsink::ReportSink;
+use std::sync::mpsc::{self, RecvTimeoutError, Receiver};
 use libc; // 0.2.66
-use nix::ioctl_read; // 0.16.1
-use std::{
-    os::unix::{fs::OpenOptionsExt, io::AsRawFd},
-};
-use std::thread;
+use std::time::{Duration, Instant};
 use fobword_core::converter::*;
-use std::sync::{Arc, atomic::AtomicBool};
-use notify::{Watcher, RecursiveMode, watcher};
+use serde::{Deserialize, Serialize};
 
+/// How long the last typed character of a secret is shown with `Mask::LastCharacter`.
+const REVEAL_LAST_CHARACTER: Duration = Duration::from_millis(800);
 
 
-ioctl_read!(hid_read_sz, b'H', 0x01, libc::c_int);
-ioctl_read!(hid_read_descr, b'H', 0x02, hidraw_report_descriptor);
-
-const HID_MAX_DESCRIPTOR_SIZE: usize = 4096;
-
-#[repr(C)]
-pub struct hidraw_report_descriptor {
-    size: u32,
-    value: [u8; HID_MAX_DESCRIPTOR_SIZE],
+/// Decides which device and host events lock the device while it is unlocked.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LockPolicy
+{
+    pub keyboard_removed: bool,
+    pub host_suspended: bool,
+    pub host_disconnected: bool,
 }
 
+impl Default for LockPolicy
+{
+    fn default() -> LockPolicy
+    {
+        LockPolicy { keyboard_removed: true, host_suspended: true, host_disconnected: true }
+    }
+}
 
-#[derive(Debug, PartialEq)]
-pub enum Events
+impl LockPolicy
 {
-    ModifierUp(u8),
-    ModifierDown(u8),
-    KeyUp(u8),
-    KeyDown(u8),
+    /// Check if the event should lock the device.
+    pub fn locks_on(&self, event: &Events) -> bool
+    {
+        match event
+        {
+            Events::DeviceRemoved(_) => self.keyboard_removed,
+            Events::HostSuspended => self.host_suspended,
+            Events::HostDisconnected => self.host_disconnected,
+            _ => false,
+        }
+    }
 }
 
 /// Struct that contains helper functions to simplify reading and writing HID reports
 pub struct IOhelper
 {
-    /// The writer to which HID reports will be written
-    pub output_file: File,
+    /// The gadget to which HID reports will be written
+    pub output: ReportSink,
+
+    /// The kind of report the gadget takes
+    protocol: ReportProtocol,
 
-    receiver: Receiver<Events>,
+    receiver: Receiver<InputEvent>,
+
+    /// Stops and joins the input thread when the helper is dropped
+    #[allow(dead_code)]
+    reactor: ReactorHandle,
 
     modifier_state: u8,
 
     keys_held: Vec<u8>,
 
+    /// How long to wait for input before giving up, wait forever if not set
+    timeout: Option<Duration>,
+
+    /// Which events lock the device, no events lock it if not set
+    lock_policy: Option<LockPolicy>,
+
+    /// How secrets are shown while they are typed
+    mask: Mask,
+
     pub converter: Converter,
 
     pub window: Window,
@@ -59,13 +86,43 @@ pub struct IOhelper
 impl IOhelper
 {
     /// Create a new helper from stuff
-    pub(crate) fn new(gadget_path: &str, converter: Converter, window: Window) -> std::io::Result<IOhelper>
+    pub(crate) fn new(gadget_path: &Path, protocol: ReportProtocol, backend: InputBackend, converter: Converter, window: Window) -> std::io::Result<IOhelper>
     {
-        let receiver = IOhelper::init_hidraw_readers2()?;
-        let output_file = OpenOptions::new().write(true).open(gadget_path)?;
+        let (sender, receiver) = mpsc::channel();
+        let reactor = Reactor::spawn(sender, backend)?;
+        let output = ReportSink::open(gadget_path, protocol)?;
         let modifier_state = 0u8;
         let keys_held = Vec::new();
-        Ok(IOhelper { output_file, receiver, modifier_state, keys_held, converter, window })
+        let timeout = None;
+        let lock_policy = None;
+        let mask = Mask::default();
+        Ok(IOhelper { output, protocol, receiver, reactor, modifier_state, keys_held, timeout, lock_policy, mask, converter, window })
+    }
+
+    /// The kind of report the gadget takes.
+    pub fn protocol(&self) -> ReportProtocol
+    {
+        self.protocol
+    }
+
+    /// Set which events make a read fail with `DataHandleError::Locked`, `None` ignores them.
+    pub fn set_lock_policy(&mut self, lock_policy: Option<LockPolicy>)
+    {
+        self.lock_policy = lock_policy;
+    }
+
+    /// Check if a write error means the host went away.
+    pub fn is_host_disconnect(error: &io::Error) -> bool
+    {
+        matches!(error.raw_os_error(), Some(libc::ESHUTDOWN) | Some(libc::EPIPE) | Some(libc::ENODEV) | Some(libc::ECONNRESET))
+    }
+
+    /// Set how long to wait for input before a read fails with a `RecvTimeoutError`.
+    ///
+    /// The timeout starts over with every input event, `None` waits forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>)
+    {
+        self.timeout = timeout;
     }
 
     pub fn clear_screen(&mut self)
@@ -86,306 +143,322 @@ impl IOhelper
             {
                 if key == self.converter.get_key(&(Modifier::from(self.modifier_state), k))
                 {
-                    self.write_key(&Key::Undefined(Modifier::NoModifier, 0))?;
+                    self.write_passthrough(|io| io.write_key(&Key::Undefined(Modifier::NONE, 0)))?;
                     break;
                 }
             }
-            self.write_keys_to_output()?;
+            self.write_passthrough(|io| io.write_keys_to_output())?;
         }
         Ok(())
     }
 
-    fn process_input(&mut self) -> Result<Events, std::sync::mpsc::RecvError> 
+    /// Pass input on to the host, a host that is not there can't receive it so that is not an error.
+    fn write_passthrough<F>(&mut self, write: F) -> std::io::Result<()>
+    where F: FnOnce(&mut IOhelper) -> std::io::Result<usize>
     {
-        let event = self.receiver.recv()?;
-        match event
+        match write(self)
         {
-            Events::ModifierDown(pressed_modifier) => self.modifier_state |= pressed_modifier,
-            Events::ModifierUp(released_modifier) => self.modifier_state ^= released_modifier,
-            Events::KeyDown(pressed_key) => self.keys_held.push(pressed_key),
-            Events::KeyUp(released_key) => self.keys_held.retain(|x| x != &released_key),
-        };
-        Ok(event)
+            Err(e) if !IOhelper::is_host_disconnect(&e) => Err(e),
+            _ => Ok(()),
+        }
     }
 
-    pub fn next_key(&mut self) -> Result<Key, DataHandleError>
+    fn process_input(&mut self) -> Result<Events, DataHandleError> 
     {
-        loop 
+        loop
         {
-            if let Events::KeyDown(k) = self.process_input()?
+            if let Some(event) = self.process_input_before(None)?
             {
-                return Ok(self.converter.get_key(&(Modifier::from(self.modifier_state), k)))
+                return Ok(event)
             }
         }
     }
 
-    pub fn screen_on(&mut self) -> std::io::Result<usize>
+    /// Wait for the next event, returns `None` when the deadline passed first.
+    ///
+    /// The lock timeout only applies when there is no deadline, the deadlines used are shorter.
+    fn process_input_before(&mut self, deadline: Option<Instant>) -> Result<Option<Events>, DataHandleError>
     {
-        self.window.awaken()
+        let event = match (deadline, self.timeout)
+        {
+            (Some(deadline), _) => match self.receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                Ok(input) => input.event,
+                Err(RecvTimeoutError::Timeout) => return Ok(None),
+                Err(error) => return Err(error.into()),
+            },
+            (None, Some(timeout)) => self.receiver.recv_timeout(timeout)?.event,
+            (None, None) => self.receiver.recv()?.event,
+        };
+        self.update_state(&event);
+        if let Some(policy) = &self.lock_policy
+        {
+            if policy.locks_on(&event)
+            {
+                return Err(DataHandleError::Locked(format!("{:?}", event)))
+            }
+        }
+        Ok(Some(event))
     }
 
-    pub fn screen_off(&mut self) -> std::io::Result<usize>
+    fn update_state(&mut self, event: &Events)
     {
-        self.window.sleep()
+        match *event
+        {
+            Events::ModifierDown(pressed_modifier) => self.modifier_state |= pressed_modifier.bit(),
+            Events::ModifierUp(released_modifier) => self.modifier_state &= !released_modifier.bit(),
+            Events::KeyDown(pressed_key) => self.keys_held.push(pressed_key),
+            Events::KeyUp(released_key) => self.keys_held.retain(|x| x != &released_key),
+            _ => (),
+        };
     }
 
-    fn init_hidraw_readers2() -> std::io::Result<Receiver<Events>>
+    /// Process all queued input, keeping track of held keys without acting on them.
+    pub fn discard_input(&mut self)
     {
-        let files = std::fs::read_dir("/dev/")?;
-
-        let (sender, receiver) = mpsc::channel();
-
-        let mut threads = HashMap::new();
-
-        // Check all existing files
+        while let Ok(input) = self.receiver.try_recv()
+        {
+            self.update_state(&input.event);
+        }
+    }
 
-        for f in files
+    /// Wait for the next key press, returns `None` when the deadline passed first.
+    fn next_key_before(&mut self, deadline: Option<Instant>) -> Result<Option<Key>, DataHandleError>
+    {
+        loop 
         {
-            let file_path = f?.path();
-            if let Some(file) = IOhelper::validate_file(&file_path)?
+            match self.process_input_before(deadline)?
             {
-                IOhelper::starting_thread(file_path, file, &mut threads, &sender)
+                Some(Events::KeyDown(k)) => return Ok(Some(self.converter.get_key(&(Modifier::from(self.modifier_state), k)))),
+                Some(_) => continue,
+                None => return Ok(None),
             }
         }
+    }
 
-  
-    
-        thread::spawn(move || 
-        {              
-            let (tx, rx) = mpsc::channel();
-            let mut watcher = watcher(tx, std::time::Duration::from_secs(1)).unwrap();
-            watcher.watch("/dev/", RecursiveMode::NonRecursive).unwrap();
-            loop {
-                match rx.recv() {
-                Ok(event) => {
-                    match event
-                    {
-                            notify::DebouncedEvent::Create(path) => 
-                            {
-                                if let Ok(Some(file)) = IOhelper::validate_file(&path)
-                                {
-                                    IOhelper::starting_thread(path, file, &mut threads, &sender)
-                                }
-                            }
-                            notify::DebouncedEvent::Remove(path) => IOhelper::stopping_thread(path, &mut threads),
-                            _ => continue,
-                    };
-                },
-                Err(e) => println!("watch error: {:?}", e),
-                }
+    /// Set how secrets are shown while they are typed.
+    pub fn set_mask(&mut self, mask: Mask)
+    {
+        self.mask = mask;
+    }
+
+    /// Wait for the next key press and return the modifier state and key code as they are in the HID report.
+    pub fn next_raw_key(&mut self) -> Result<(u8, u8), DataHandleError>
+    {
+        loop 
+        {
+            if let Events::KeyDown(k) = self.process_input()?
+            {
+                return Ok((self.modifier_state, k))
             }
         }
-        );
+    }
 
-        Ok(receiver)
+    pub fn screen_on(&mut self) -> std::io::Result<usize>
+    {
+        self.window.awaken()
     }
 
-    fn starting_thread(path: PathBuf, mut file: File, threads: &mut HashMap<PathBuf, Arc<AtomicBool>>, sender: &Sender<Events>)
+    pub fn screen_off(&mut self) -> std::io::Result<usize>
     {
-        let stop_flag = Arc::new(AtomicBool::new(false));
+        self.window.sleep()
+    }
 
-        thread::spawn(
-            {
-                let stop_flag = stop_flag.clone();
-                let thread_sender = sender.clone();
-                let mut buffer = vec![0u8;8];
-                let mut old_buffer = vec![0u8;8];
-                move ||
-                loop 
-                {
-                    if stop_flag.load(std::sync::atomic::Ordering::Relaxed)
-                    {
-                        break;
-                    }
-                    if let Ok(_) = file.read(&mut buffer)
-                    {
-                        IOhelper::generate_events(&buffer, &old_buffer, &thread_sender);
-                        std::mem::swap(&mut buffer, &mut old_buffer);
-                    }
-                }
-            }
-        );
-        threads.insert(path, stop_flag);
+    pub fn write_key(&mut self, key: &Key) -> std::io::Result<usize>
+    {
+        let output = self.key_report(key);
+        self.output.write_report(output.as_bytes())
     }
 
-    fn stopping_thread(path: PathBuf, threads: &mut HashMap<PathBuf, Arc<AtomicBool>>)
+    /// Press and release a key on the host.
+    pub fn tap_key(&mut self, key: &Key) -> std::io::Result<()>
     {
-        if let Some(stop_flag) = threads.get(&path)
-        {
-            stop_flag.store(true, std::sync::atomic::Ordering::Relaxed);
-        };
+        let output = self.key_report(key);
+        self.output.write_reports([output, KeyboardReport::new(self.protocol)].iter())
     }
 
-    fn init_hidraw_readers() -> std::io::Result<Receiver<Events>>
+    /// The report with only this key pressed.
+    fn key_report(&self, key: &Key) -> KeyboardReport
     {
-        let (sender, receiver) = mpsc::channel();
-        let files = IOhelper::viable_files()?;
+        let (modi, key_code) = self.converter.get_raw(key);
+        let mut report = KeyboardReport::new(self.protocol);
+        report.set_modifiers(modi.bits());
+        report.press(key_code);
+        report
+    }
 
-        for mut file in files
+    /// Send the held keys to the host, translated to the output layout.
+    ///
+    /// A boot report can't hold more than 6 keys, the host is told so instead of some keys being dropped.
+    pub fn write_keys_to_output(&mut self) -> std::io::Result<usize>
+    {
+        let mut builder = ReportBuilder::new(self.protocol);
+        builder.set_modifiers(self.modifier_state);
+        for key_code in self.keys_held.iter()
         {
-            let thread_sender = sender.clone();
-            let mut buffer = vec![0u8;8];
-            let mut old_buffer = vec![0u8;8];
-            thread::spawn(move || 
-            {
-                loop
-                {
-                    // Fix this garbage maybe?
-                    if let Ok(_) = file.read(&mut buffer)
-                    {
-                        IOhelper::generate_events(&buffer, &old_buffer, &thread_sender);
-                        std::mem::swap(&mut buffer, &mut old_buffer);
-                    }
-                }
-            });
+            let key = self.converter.get_key(&(Modifier::from(self.modifier_state), *key_code));
+            let (modifier, key) = self.converter.get_raw(&key);
+            builder.set_modifiers(modifier.bits());
+            builder.press(key);
         }
-        Ok(receiver)
+        self.output.write_report(builder.build().as_bytes())
     }
 
-    fn generate_events(report: &[u8], old_report: &[u8], sender: &Sender<Events>) -> Result<(), SendError<Events>>
+    /// Read a line of text, ended with Enter or the macro key.
+    ///
+    /// The line can be edited with the arrow keys, Home, End, Delete, Ctrl-W and Ctrl-U.
+    ///
+    /// # Errors
+    /// `DataHandleError::Cancelled` when Escape is pressed, and the errors of reading input
+    pub fn read_line(&mut self) -> Result<String, DataHandleError>
     {
-        IOhelper::modifier_events(report[0], old_report[0], &sender)?;
-        IOhelper::key_events(report, old_report, &sender)
+        self.read_line_with(None, false)
     }
 
-    fn modifier_events(new: u8, old: u8, sender: &Sender<Events>) -> Result<(), SendError<Events>>
+    /// Read a line where Tab adds the completion of what was typed so far.
+    pub fn read_command(&mut self, complete: fn(&str) -> Option<String>) -> Result<String, DataHandleError>
     {
-        use std::cmp::Ordering::{Equal, Less, Greater};
-        match new.cmp(&old)
-        {
-            Equal => Ok(()),
-            Less => sender.send(Events::ModifierUp(old - new)),
-            Greater => sender.send(Events::ModifierDown(new - old)),
-        }
+        self.read_line_with(Some(complete), false)
     }
 
-    fn key_events(report: &[u8], old_report: &[u8], sender: &Sender<Events>) -> Result<(), SendError<Events>>
+    /// Read a password or other secret, it is masked on the screen as set with [`IOhelper::set_mask`].
+    ///
+    /// Ctrl-R shows or hides what was typed.
+    pub fn read_secret(&mut self) -> Result<String, DataHandleError>
     {
-        for i in report[2..].iter().filter(|x| !old_report.contains(x))
-        {
-            sender.send(Events::KeyDown(*i))?;
-        }
-        for i in old_report[2..].iter().filter(|x| !report.contains(x))
-        {
-            sender.send(Events::KeyUp(*i))?;
-        }
-        Ok(())
+        self.read_line_with(None, true)
     }
 
-    fn validate_file(path: &PathBuf)-> std::io::Result<Option<File>>
+    fn read_line_with(&mut self, complete: Option<fn(&str) -> Option<String>>, secret: bool) -> Result<String, DataHandleError>
     {
-        if path.to_str().expect("what").contains("hidraw")
+        let mut editor = LineEditor::new();
+        let mut shown = String::new();
+        let mut revealed = false;
+        // When the last typed character of a secret has to be masked again
+        let mut hide_at = None;
+        loop 
         {
-            let file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .custom_flags(libc::O_NONBLOCK)
-            .open(path)?;
-            let fd = file.as_raw_fd();
-    
-            let mut size = 0;
-            unsafe { hid_read_sz(fd, &mut size)?; }
-            
-            let mut desc_raw = hidraw_report_descriptor { size: size as u32, value: [0u8; HID_MAX_DESCRIPTOR_SIZE] };
-            unsafe { hid_read_descr(fd, &mut desc_raw)?; }
-            let data = &desc_raw.value[..desc_raw.size as usize];
-
-            // Is keyboard?
-            if data[3] == 6
+            let key = self.next_key_before(hide_at)?;
+            hide_at = None;
+            let key = match key
+            {
+                Some(key) => key,
+                None =>
+                {
+                    self.redraw(&mut shown, editor.display_masked(false))?;
+                    continue;
+                },
+            };
+            match key
             {
-                return Ok(Some(file));
+                Key::Enter | Key::Macro =>
+                {
+                    editor.apply(Edit::End);
+                    let line = if secret && !revealed { editor.display_masked(false) } else { editor.display() };
+                    self.redraw(&mut shown, line)?;
+                    self.window.print_write_buffer()?;
+                    return Ok(editor.text())
+                },
+                _ if secret && self.ctrl_shortcut(&key) == Some('r') => revealed = !revealed,
+                Key::Char('\t') if complete.is_some() =>
+                {
+                    if editor.at_end()
+                    {
+                        let completion = complete.and_then(|complete| complete(&editor.text())).unwrap_or_default();
+                        editor.insert_str(&completion);
+                    }
+                },
+                _ if self.unmodified(&key) == Key::Escape =>
+                {
+                    self.redraw(&mut shown, String::new())?;
+                    return Err(DataHandleError::Cancelled)
+                },
+                key =>
+                {
+                    if let Some(edit) = self.edit_for(&key)
+                    {
+                        editor.apply(edit);
+                        if let Edit::Insert(_) = edit
+                        {
+                            if secret && !revealed && self.mask == Mask::LastCharacter
+                            {
+                                hide_at = Some(Instant::now() + REVEAL_LAST_CHARACTER);
+                            }
+                        }
+                    }
+                },
             }
+            let line = if secret && !revealed { editor.display_masked(hide_at.is_some()) } else { editor.display() };
+            self.redraw(&mut shown, line)?;
         }
-        Ok(None)
     }
 
-    fn viable_files()-> std::io::Result<Vec<File>>
+    /// The line edit a key stands for, `None` if it is not used for editing.
+    fn edit_for(&self, key: &Key) -> Option<Edit>
     {
-        let mut viable_files = Vec::new();
-        let files = std::fs::read_dir("/dev/")?;
-        for f in files
+        match *key
         {
-            let f = f?.path();
-            if f.to_str().expect("what").contains("hidraw")
+            Key::Char(c) => Some(Edit::Insert(c)),
+            Key::Backspace => Some(Edit::Backspace),
+            _ => match self.unmodified(key)
             {
-                let file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .custom_flags(libc::O_NONBLOCK)
-                .open(f)?;
-                let fd = file.as_raw_fd();
-        
-                let mut size = 0;
-                unsafe { hid_read_sz(fd, &mut size)?; }
-                
-                let mut desc_raw = hidraw_report_descriptor { size: size as u32, value: [0u8; HID_MAX_DESCRIPTOR_SIZE] };
-                unsafe { hid_read_descr(fd, &mut desc_raw)?; }
-                let data = &desc_raw.value[..desc_raw.size as usize];
-
-                // Is keyboard?
-                if data[3] == 6
+                Key::Right => Some(Edit::Right),
+                Key::Left => Some(Edit::Left),
+                Key::Home => Some(Edit::Home),
+                Key::End => Some(Edit::End),
+                Key::Delete => Some(Edit::Delete),
+                _ => match self.ctrl_shortcut(key)
                 {
-                    viable_files.push(file);
-                }
-                
-            }
+                    Some('w') => Some(Edit::DeleteWord),
+                    Some('u') => Some(Edit::DeleteToStart),
+                    _ => None,
+                },
+            },
         }
-
-        Ok(viable_files)
     }
 
-    /// Write multiple buffers to the file, flushing between every buffer, as to simulate a single HID report
-    pub fn write_buffers_to_file(&mut self, buffers: Vec<Vec<u8>>) -> std::io::Result<()>
+    /// The key without the modifiers it was pressed with, Shift-Home is still Home.
+    fn unmodified(&self, key: &Key) -> Key
     {
-        for i in buffers
+        match *key
         {
-            self.output_file.write(&i)?;
+            Key::Undefined(_, key_code) => self.converter.get_key(&(Modifier::NONE, key_code)),
+            key => key,
         }
-        Ok(())
     }
 
-    pub fn write_key(&mut self, key: &Key) -> std::io::Result<usize>
+    /// The character of a key pressed with Ctrl, `None` for other keys.
+    ///
+    /// Ctrl shortcuts follow the layout, Ctrl-W is where the layout has its w.
+    fn ctrl_shortcut(&self, key: &Key) -> Option<char>
     {
-        let (modi, key_code) = self.converter.get_raw(key);
-        let output = vec![modi as u8, 0, key_code, 0, 0 ,0 ,0 ,0];
-        self.output_file.write(&output)
-    }
-
-    pub fn write_keys_to_output(&mut self) -> std::io::Result<usize>
-    {
-        let mut output = vec![self.modifier_state, 0, 0, 0, 0, 0, 0, 0];
-        for (index, key_code) in IOhelper::last_x_or_all(&self.keys_held, 6).enumerate()
+        match *key
         {
-            let key = self.converter.get_key(&(Modifier::from(self.modifier_state), *key_code));
-            let (modifier, key) = self.converter.get_raw(&key);
-            output[0]  = modifier as u8;
-            output[2 + index] = key
-            
+            Key::Undefined(modifier, key_code) if modifier.ctrl() =>
+            {
+                match self.converter.get_key(&(Modifier::NONE, key_code))
+                {
+                    Key::Char(c) => Some(c),
+                    _ => None,
+                }
+            },
+            _ => None,
         }
-        self.output_file.write(&output)
     }
-    
-    fn last_x_or_all<T>(collection: &[T], size: usize) -> std::slice::Iter<T>
+
+    /// Change the line on the screen from what is shown to the new text, only redrawing the characters that changed.
+    fn redraw(&mut self, shown: &mut String, text: String) -> Result<(), DataHandleError>
     {
-        if collection.len() > size
+        let same = shown.chars().zip(text.chars()).take_while(|(a, b)| a == b).count();
+        for _ in same..shown.chars().count()
         {
-            return collection[collection.len() - size..].iter()
+            self.window.remove_char();
         }
-        collection.iter()
-    }
-
-    pub fn read_line(&mut self) -> Result<String, DataHandleError>
-    {
-        let mut result = String::new();
-        loop 
+        for c in text.chars().skip(same)
         {
-            match self.next_key()?
-            {
-                Key::Enter | Key::Macro => { self.window.print_write_buffer()?; return Ok(result) },
-                Key::Char(c) => { self.window.add_char(c)?; result.push(c) },
-                Key::Backspace => { self.window.remove_char(); result.pop(); },
-                _ => continue,
-            }
+            self.window.add_char(c)?;
         }
+        *shown = text;
+        Ok(())
     }
 }
\ No newline at end of file
@@ -1,55 +1,165 @@
 mod app;
 mod converterutilities;
+mod fileutilities;
 mod iohelper;
+mod paths;
+mod reactor;
+mod reportsink;
+mod settings;
+mod wizard;
 
-use app::{App, AppSettings};
-use iohelper::IOhelper;
+use app::App;
+use iohelper::{IOhelper, LockPolicy};
+use paths::Paths;
+use settings::AppSettings;
 
-use std::fs::{File, OpenOptions};
-use std::io::Read;
+use std::path::Path;
 
-use fobword_core::config::{Config, DataInformation, LockedData};
+use fobword_core::config::{Config, LockedData, DAMAGED_VAULT};
 use fobword_core::error::DataHandleError;
 
 fn main() -> Result<(), DataHandleError>
 {
-    let mut app = match OpenOptions::new().read(true).open("/usr/bin/config.yaml")
-    {
-        Ok(mut file) => existing_config(&mut file)?,
-        Err(e) => {
-            match e.kind()
-            {
-                std::io::ErrorKind::NotFound => default_config(),
-                _ => Err(DataHandleError::IOError(e)),
-            }?
+    let paths = Paths::from_env();
+    std::fs::create_dir_all(paths.config_directory())?;
+    std::fs::create_dir_all(paths.vault_directory())?;
+    split_combined_config(&paths)?;
+
+    let settings = match load_with_backups(&paths.config, AppSettings::from_yaml)?
+    {
+        Some(settings) => settings,
+        None =>
+        {
+            let settings = AppSettings::new(&paths);
+            fileutilities::atomic_write(&paths.config, settings.to_yaml()?.as_bytes())?;
+            settings
         }
     };
+    settings.validate_layouts(&paths)?;
+
+    // Without a usable vault or back-up App starts the first run wizard, which offers to import a vault
+    let data = match load_with_backups(&paths.vault, LockedData::from_yaml)
+    {
+        Ok(data) => data,
+        Err(e) if is_damaged(&e) =>
+        {
+            println!("{} and its back-ups could not be loaded: {:?}", paths.vault.display(), e);
+            None
+        },
+        Err(e) => return Err(e),
+    };
+    let mut app = App::new(settings, data, paths)?;
     app.main_loop()?;
     Ok(())
 }
 
-fn existing_config(file: &mut File) -> Result<App, DataHandleError>
+/// Split a config file that holds both the settings and the vault into a settings file and a vault file.
+///
+/// The combined file is kept as a back-up of the settings file, so a power cut halfway loses nothing.
+fn split_combined_config(paths: &Paths) -> Result<(), DataHandleError>
+{
+    let buffer = match std::fs::read_to_string(&paths.config)
+    {
+        Ok(buffer) => buffer,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(DataHandleError::IOError(e)),
+    };
+    // A settings file has neither key, so it deserializes into an empty Config
+    let (settings, data) = match Config::<AppSettings>::from_yaml(&buffer)
+    {
+        Ok(Config { settings: Some(settings), data: Some(data) }) => (settings, data),
+        _ => return Ok(()),
+    };
+
+    println!("Splitting {} into a settings and a vault file", paths.config.display());
+    fileutilities::rotate_backups(&paths.config, settings.backup_generations.max(1))?;
+    // A vault that exists was written by a split that was cut short
+    if !paths.vault.exists()
+    {
+        fileutilities::atomic_write(&paths.vault, data.to_yaml()?.as_bytes())?;
+    }
+    fileutilities::atomic_write(&paths.config, settings.to_yaml()?.as_bytes())?;
+    Ok(())
+}
+
+/// Load a file, falling back to the newest valid back-up when it is missing or can't be parsed.
+///
+/// Returns `None` if there is neither a file nor a back-up, a file that parses but can't be used is returned as an error.
+fn load_with_backups<T, F>(path: &Path, parse: F) -> Result<Option<T>, DataHandleError>
+where
+    F: Fn(&str) -> Result<T, DataHandleError>,
+{
+    let error = match read_with(path, &parse)
+    {
+        Ok(value) => return Ok(Some(value)),
+        Err(DataHandleError::IOError(e)) if e.kind() == std::io::ErrorKind::NotFound => None,
+        // Damaged files are replaced by a back-up, settings the user got wrong are reported
+        Err(e) if is_damaged(&e) => Some(e),
+        Err(e) => return Err(e),
+    };
+
+    for backup in fileutilities::backups(path)?
+    {
+        if let Ok(value) = read_with(&backup, &parse)
+        {
+            println!("{} could not be loaded, using back-up {}", path.display(), backup.display());
+            return Ok(Some(value))
+        }
+    }
+    match error
+    {
+        Some(e) => Err(e),
+        None => Ok(None),
+    }
+}
+
+/// Check if a file could not be loaded because it is damaged, rather than missing or holding unusable values.
+fn is_damaged(error: &DataHandleError) -> bool
 {
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer)?;
-    let config = Config::from_yaml(&buffer)?;
-    App::new(config.settings.unwrap(), config.data.unwrap())
+    match error
+    {
+        DataHandleError::YamlParseError(_) | DataHandleError::B64ParseError(_) | DataHandleError::AesError(_) => true,
+        DataHandleError::LockedData(message) => message == DAMAGED_VAULT,
+        DataHandleError::IOError(e) => e.kind() == std::io::ErrorKind::InvalidData,
+        _ => false,
+    }
 }
 
-fn default_config() -> Result<App, DataHandleError>
+fn read_with<T, F>(path: &Path, parse: &F) -> Result<T, DataHandleError>
+where
+    F: Fn(&str) -> Result<T, DataHandleError>,
 {
-    let default_settings = 
-        AppSettings { 
-            output: "/dev/hidg0".to_owned(),
-            macro_key: vec![0x02, 0, 0x3au8, 0, 0, 0, 0, 0,],
-            input_location: "/usr/bin/qwerty-layout.txt".to_owned(),
-            output_location: "/usr/bin/qwerty-layout.txt".to_owned(),
-        };
-
-    let default_password = "password";
-    let mut locked_data = LockedData::new(default_password)?;
-    let mut data = locked_data.unlock(default_password)?;
-    data.insert(String::from("test"), DataInformation::new(None, None, String::from("some_password")));
-    locked_data.lock(default_password, data)?;
-    App::new(default_settings, locked_data)
-}
\ No newline at end of file
+    let buffer = std::fs::read_to_string(path)?;
+    parse(&buffer)
+}
+
+#[cfg(test)]
+mod maintests
+{
+    use super::*;
+
+    #[test]
+    fn test_damaged_vault_loads_newest_backup()
+    {
+        let directory = std::env::temp_dir().join(format!("fobword-main-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("vault.yaml");
+
+        let older = LockedData::new("older").unwrap().to_yaml().unwrap();
+        let newer = LockedData::new("newer").unwrap().to_yaml().unwrap();
+        for buffer in [&older, &newer].iter()
+        {
+            fileutilities::atomic_write(&path, buffer.as_bytes()).unwrap();
+            fileutilities::rotate_backups(&path, 2).unwrap();
+        }
+        // A damaged sector in the middle of the vault
+        let mut damaged = newer.clone().into_bytes();
+        damaged[newer.find("vault: ").unwrap() + 100] = b'*';
+        fileutilities::atomic_write(&path, &damaged).unwrap();
+
+        let loaded = load_with_backups(&path, LockedData::from_yaml).unwrap().unwrap();
+        assert_eq!(newer, loaded.to_yaml().unwrap());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
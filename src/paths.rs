@@ -0,0 +1,308 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::fileutilities;
+
+
+/// Where the config file holding both the settings and the vault used to live,
+/// it is still used if it exists and nothing else was configured.
+const LEGACY_CONFIG: &str = "/usr/bin/config.yaml";
+/// Config directory for the system wide instance.
+const SYSTEM_DIRECTORY: &str = "/var/lib/fobword";
+
+const USAGE: &str = "Usage: dys-template [OPTIONS]
+
+Options:
+    --config <path>     Settings file                                [env: FOBWORD_CONFIG]
+    --vault <path>      Vault file, next to the settings by default  [env: FOBWORD_VAULT]
+    --layout <path>     Keyboard layout, overrides the settings      [env: FOBWORD_LAYOUT]
+    --gadget <path>     HID gadget to write to, overrides settings   [env: FOBWORD_GADGET]
+    --display <path>    I2C device of the SSD1306 display            [env: FOBWORD_DISPLAY]
+    -h, --help          Print this message
+
+Without --config the file is read from $XDG_CONFIG_HOME/fobword, ~/.config/fobword
+for users other than root, or /var/lib/fobword.";
+
+/// The locations of the files used by the program.
+///
+/// Every path can be set with a command line argument or an environment variable, the argument wins if both are set.
+#[derive(Debug, Clone)]
+pub struct Paths
+{
+    /// The settings file
+    pub config: PathBuf,
+    /// The file holding the locked data
+    pub vault: PathBuf,
+    /// The failed unlock counter, kept next to the vault
+    pub lockout: PathBuf,
+    /// The I2C device of the display
+    pub display: String,
+    /// Overrides the input and output layout in the settings when set
+    pub layout: Option<PathBuf>,
+    /// Overrides the HID gadget in the settings when set
+    pub gadget: Option<PathBuf>,
+}
+
+impl Paths
+{
+    /// Read the paths from the command line arguments and environment variables of this process.
+    ///
+    /// Prints the usage and exits on `--help` or an unknown argument.
+    pub fn from_env() -> Paths
+    {
+        match Paths::parse(std::env::args().skip(1), |name| std::env::var_os(name).map(PathBuf::from))
+        {
+            Ok(paths) => paths,
+            Err(message) =>
+            {
+                let help = message.is_empty();
+                if !help
+                {
+                    eprintln!("{}\n", message);
+                }
+                eprintln!("{}", USAGE);
+                std::process::exit(if help { 0 } else { 2 });
+            }
+        }
+    }
+
+    /// Resolve the paths from arguments, falling back on the environment and then the defaults.
+    ///
+    /// Returns an empty message when help was requested.
+    fn parse<I, F>(args: I, env: F) -> Result<Paths, String>
+    where
+        I: IntoIterator<Item = String>,
+        F: Fn(&str) -> Option<PathBuf>,
+    {
+        let mut config = env("FOBWORD_CONFIG");
+        let mut vault = env("FOBWORD_VAULT");
+        let mut layout = env("FOBWORD_LAYOUT");
+        let mut gadget = env("FOBWORD_GADGET");
+        let mut display = env("FOBWORD_DISPLAY");
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next()
+        {
+            let target = match arg.as_str()
+            {
+                "--config" => &mut config,
+                "--vault" => &mut vault,
+                "--layout" => &mut layout,
+                "--gadget" => &mut gadget,
+                "--display" => &mut display,
+                "-h" | "--help" => return Err(String::new()),
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            };
+            match args.next()
+            {
+                Some(value) => *target = Some(PathBuf::from(value)),
+                None => return Err(format!("Missing value for '{}'", arg)),
+            }
+        }
+
+        let config = config.unwrap_or_else(|| Paths::default_config(&env));
+        let vault = vault.unwrap_or_else(|| fileutilities::directory(&config).join("vault.yaml"));
+        let lockout = fileutilities::directory(&vault).join("lockout.yaml");
+        let display = display.map_or_else(|| "/dev/i2c-0".to_owned(), |display| display.to_string_lossy().into_owned());
+        Ok(Paths { config, vault, lockout, display, layout, gadget })
+    }
+
+    /// The directory holding the settings file, new layouts go here.
+    pub fn config_directory(&self) -> &Path
+    {
+        fileutilities::directory(&self.config)
+    }
+
+    /// The directory holding the vault, its back-ups and the failed unlock counter.
+    pub fn vault_directory(&self) -> &Path
+    {
+        fileutilities::directory(&self.vault)
+    }
+
+    /// A vault to import, put next to the vault by hand.
+    pub fn import(&self) -> PathBuf
+    {
+        self.vault_directory().join("import.yaml")
+    }
+
+    /// Every file other than the vault that can hold a copy of it, erased together with the vault.
+    pub fn vault_backups(&self) -> io::Result<Vec<PathBuf>>
+    {
+        let mut backups = fileutilities::backups(&self.vault)?;
+        // Back-ups of the settings file can be config files that held the vault as well
+        backups.extend(fileutilities::backups(&self.config)?);
+        // Written by versions that kept a single back-up
+        backups.push(self.config_directory().join("back-up-config.yaml"));
+        backups.push(self.import());
+        Ok(backups)
+    }
+
+    /// The layout used for new settings, the one next to the config file if it exists.
+    pub fn default_layout(&self) -> PathBuf
+    {
+        if let Some(layout) = &self.layout
+        {
+            return layout.clone()
+        }
+        let layout = self.config_directory().join("qwerty-layout.txt");
+        if layout.exists()
+        {
+            return layout
+        }
+        PathBuf::from("/usr/bin/qwerty-layout.txt")
+    }
+
+    fn default_config<F>(env: &F) -> PathBuf
+    where
+        F: Fn(&str) -> Option<PathBuf>,
+    {
+        let directory = match (env("XDG_CONFIG_HOME"), env("HOME"))
+        {
+            (Some(config_home), _) => config_home.join("fobword"),
+            // root runs the device, it keeps its data in /var/lib
+            (None, Some(home)) if !Paths::is_root() => home.join(".config").join("fobword"),
+            _ => PathBuf::from(SYSTEM_DIRECTORY),
+        };
+        Paths::or_legacy(directory.join("config.yaml"), Path::new(LEGACY_CONFIG))
+    }
+
+    /// The config file, or the legacy config file when only that one exists.
+    fn or_legacy(config: PathBuf, legacy: &Path) -> PathBuf
+    {
+        if !config.exists() && legacy.exists()
+        {
+            return legacy.to_path_buf()
+        }
+        config
+    }
+
+    fn is_root() -> bool
+    {
+        unsafe { libc::geteuid() == 0 }
+    }
+}
+
+#[cfg(test)]
+mod pathstests
+{
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String>
+    {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn env<'a>(variables: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<PathBuf> + 'a
+    {
+        move |name| variables.iter().find(|(key, _)| *key == name).map(|(_, value)| PathBuf::from(value))
+    }
+
+    /// An empty directory for one test.
+    fn temp_directory(name: &str) -> PathBuf
+    {
+        let directory = std::env::temp_dir().join(format!("fobword-paths-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_flags_win_over_env()
+    {
+        let variables = [("FOBWORD_CONFIG", "/env/config.yaml"), ("FOBWORD_GADGET", "/dev/hidg1")];
+        let paths = Paths::parse(args(&["--config", "/flag/config.yaml", "--gadget", "/dev/hidg2"]), env(&variables)).unwrap();
+
+        assert_eq!(PathBuf::from("/flag/config.yaml"), paths.config);
+        assert_eq!(Some(PathBuf::from("/dev/hidg2")), paths.gadget);
+    }
+
+    #[test]
+    fn test_env_is_used_without_flags()
+    {
+        let variables = [("FOBWORD_CONFIG", "/env/config.yaml"), ("FOBWORD_VAULT", "/data/vault.yaml"), ("FOBWORD_DISPLAY", "/dev/i2c-1")];
+        let paths = Paths::parse(args(&[]), env(&variables)).unwrap();
+
+        assert_eq!(PathBuf::from("/env/config.yaml"), paths.config);
+        assert_eq!(PathBuf::from("/data/vault.yaml"), paths.vault);
+        // The lockout follows the vault, not the settings
+        assert_eq!(PathBuf::from("/data/lockout.yaml"), paths.lockout);
+        assert_eq!("/dev/i2c-1", paths.display);
+        assert_eq!(None, paths.layout);
+    }
+
+    #[test]
+    fn test_vault_is_next_to_the_settings()
+    {
+        let paths = Paths::parse(args(&["--config", "/flag/config.yaml"]), env(&[])).unwrap();
+        assert_eq!(PathBuf::from("/flag/vault.yaml"), paths.vault);
+        assert_eq!(Path::new("/flag"), paths.config_directory());
+
+        let paths = Paths::parse(args(&["--config", "config.yaml"]), env(&[])).unwrap();
+        assert_eq!(PathBuf::from("./vault.yaml"), paths.vault);
+    }
+
+    #[test]
+    fn test_xdg_config_home()
+    {
+        let directory = temp_directory("xdg");
+        std::fs::create_dir_all(directory.join("fobword")).unwrap();
+        std::fs::write(directory.join("fobword").join("config.yaml"), "").unwrap();
+        let home = directory.to_string_lossy().into_owned();
+
+        let variables = [("XDG_CONFIG_HOME", home.as_str()), ("HOME", "/home/user")];
+        let paths = Paths::parse(args(&[]), env(&variables)).unwrap();
+        assert_eq!(directory.join("fobword").join("config.yaml"), paths.config);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_config_fallback()
+    {
+        let directory = temp_directory("legacy");
+        let config = directory.join("fobword").join("config.yaml");
+        let legacy = directory.join("config.yaml");
+
+        // Neither exists, a new config file is made in the new place
+        assert_eq!(config, Paths::or_legacy(config.clone(), &legacy));
+        std::fs::write(&legacy, "").unwrap();
+        assert_eq!(legacy, Paths::or_legacy(config.clone(), &legacy));
+        // Once the new config file exists the legacy one is ignored
+        std::fs::create_dir_all(directory.join("fobword")).unwrap();
+        std::fs::write(&config, "").unwrap();
+        assert_eq!(config, Paths::or_legacy(config.clone(), &legacy));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_wipe_leaves_no_vault_copy()
+    {
+        let directory = temp_directory("wipe");
+        let config = directory.join("config.yaml").to_string_lossy().into_owned();
+        let paths = Paths::parse(args(&["--config", &config]), env(&[])).unwrap();
+        for path in [&paths.vault, &paths.config].iter()
+        {
+            fileutilities::atomic_write(path, b"vault").unwrap();
+            fileutilities::rotate_backups(path, 2).unwrap();
+            fileutilities::rotate_backups(path, 2).unwrap();
+        }
+        std::fs::write(paths.config_directory().join("back-up-config.yaml"), "vault").unwrap();
+        std::fs::write(paths.import(), "vault").unwrap();
+
+        fileutilities::secure_erase(&paths.vault).unwrap();
+        for backup in paths.vault_backups().unwrap()
+        {
+            fileutilities::secure_erase(backup).unwrap();
+        }
+        let left: Vec<PathBuf> = std::fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().path()).collect();
+        assert_eq!(vec![paths.config.clone()], left);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_bad_arguments()
+    {
+        assert_eq!(Err(String::new()), Paths::parse(args(&["--help"]), env(&[])).map(|_| ()));
+        assert!(Paths::parse(args(&["--vault"]), env(&[])).is_err());
+        assert!(Paths::parse(args(&["--verbose"]), env(&[])).is_err());
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::mem;
+use std::os::unix::{fs::OpenOptionsExt, io::{AsRawFd, RawFd}};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{SendError, Sender};
+use std::thread;
+use std::time::Instant;
+
+use nix::{ioctl_read, ioctl_read_buf, ioctl_write_int};
+use nix::sys::eventfd::{eventfd, EfdFlags};
+use nix::sys::epoll::{epoll_create1, epoll_ctl, epoll_wait, EpollCreateFlags, EpollEvent, EpollFlags, EpollOp};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::sys::time::{TimeSpec, TimeValLike};
+use nix::sys::timerfd::{ClockId, Expiration, TimerFd, TimerFlags, TimerSetTimeFlags};
+
+use fobword_core::descriptor::{self, ReportFormat};
+use fobword_core::evdev::{EvdevKeyboard, EV_KEY};
+use fobword_core::input::{Events, InputEvent, InputState};
+use serde::{Deserialize, Serialize};
+
+ioctl_read!(hid_read_sz, b'H', 0x01, libc::c_int);
+ioctl_read!(hid_read_descr, b'H', 0x02, hidraw_report_descriptor);
+// EVIOCGBIT(EV_KEY, len), the key codes a device can send
+ioctl_read_buf!(evdev_key_bits, b'E', 0x20 + EV_KEY as u8, u8);
+ioctl_write_int!(evdev_grab, b'E', 0x90);
+
+const HID_MAX_DESCRIPTOR_SIZE: usize = 4096;
+
+#[repr(C)]
+pub struct hidraw_report_descriptor {
+    size: u32,
+    value: [u8; HID_MAX_DESCRIPTOR_SIZE],
+}
+
+/// Where the hidraw keyboards show up.
+const HIDRAW_DIRECTORY: &str = "/dev/";
+/// Where the evdev keyboards show up.
+const EVDEV_DIRECTORY: &str = "/dev/input/";
+/// How often the state of the usb device controller is checked, in seconds.
+const HOST_CHECK_INTERVAL: i64 = 1;
+/// How many evdev events are read at once.
+const EVENTS_PER_READ: usize = 64;
+/// Linux key codes of A and Enter, a device that has them is a keyboard.
+const KEY_A: usize = 30;
+const KEY_ENTER: usize = 28;
+
+/// Where keyboards are read from.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum InputBackend
+{
+    /// `/dev/hidraw*` nodes with a boot keyboard report descriptor
+    #[default]
+    Hidraw,
+    /// `/dev/input/event*` nodes, this includes bluetooth and composite keyboards.
+    /// The keyboards are grabbed so their keys don't reach the console as well.
+    Evdev,
+}
+
+impl InputBackend
+{
+    fn directory(self) -> &'static str
+    {
+        match self
+        {
+            InputBackend::Hidraw => HIDRAW_DIRECTORY,
+            InputBackend::Evdev => EVDEV_DIRECTORY,
+        }
+    }
+}
+
+/// Reads every keyboard, hotplug notification and host state change on one thread.
+///
+/// The thread sleeps in `epoll_wait` until one of them has something to say, then sends it on as `InputEvent`s, stamped with the moment they were read.
+/// The reports of all keyboards are merged, a keyboard that is removed releases the keys it was holding.
+/// It stops when the [`ReactorHandle`] is dropped or the receiving end of the channel is.
+pub struct Reactor
+{
+    epoll: RawFd,
+    inotify: Inotify,
+    host_timer: TimerFd,
+    backend: InputBackend,
+    /// Becomes readable when the thread has to stop, owned by the handle
+    stop: RawFd,
+    /// The keyboards being read, by file descriptor
+    devices: HashMap<RawFd, Device>,
+    input: InputState,
+    host_state: String,
+    sender: Sender<InputEvent>,
+}
+
+struct Device
+{
+    path: PathBuf,
+    file: File,
+    decoder: Decoder,
+}
+
+/// Turns what is read from a device into boot reports.
+enum Decoder
+{
+    /// The keyboard formats from the report descriptor of a hidraw node
+    Hidraw(Vec<ReportFormat>),
+    /// The key state of an evdev keyboard
+    Evdev(EvdevKeyboard),
+}
+
+/// Stops the input thread and waits for it when dropped.
+pub struct ReactorHandle
+{
+    stop: RawFd,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for ReactorHandle
+{
+    fn drop(&mut self)
+    {
+        let _ = nix::unistd::write(self.stop, &1u64.to_ne_bytes());
+        if let Some(thread) = self.thread.take()
+        {
+            let _ = thread.join();
+        }
+        let _ = nix::unistd::close(self.stop);
+    }
+}
+
+impl Reactor
+{
+    /// Open every keyboard that is plugged in and start reading on a new thread.
+    pub fn spawn(sender: Sender<InputEvent>, backend: InputBackend) -> io::Result<ReactorHandle>
+    {
+        let stop = eventfd(0, EfdFlags::EFD_CLOEXEC | EfdFlags::EFD_NONBLOCK)?;
+        // The handle only owns the stop fd once the thread runs, every error before that closes it here
+        let mut reactor = match Reactor::new(sender, backend, stop)
+        {
+            Ok(reactor) => reactor,
+            Err(e) =>
+            {
+                let _ = nix::unistd::close(stop);
+                return Err(e)
+            },
+        };
+        let thread = thread::spawn(move ||
+        {
+            if let Err(e) = reactor.run()
+            {
+                eprintln!("input stopped: {}", e);
+            }
+        });
+        Ok(ReactorHandle { stop, thread: Some(thread) })
+    }
+
+    /// Set up the reactor and open the keyboards that are plugged in.
+    fn new(sender: Sender<InputEvent>, backend: InputBackend, stop: RawFd) -> io::Result<Reactor>
+    {
+        let epoll = epoll_create1(EpollCreateFlags::EPOLL_CLOEXEC)?;
+
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+        // Permissions are set after the node is created, a node that could not be opened is tried again then
+        inotify.add_watch(backend.directory(), AddWatchFlags::IN_CREATE | AddWatchFlags::IN_ATTRIB | AddWatchFlags::IN_DELETE)?;
+
+        let host_timer = TimerFd::new(ClockId::CLOCK_MONOTONIC, TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC)?;
+        host_timer.set(Expiration::Interval(TimeSpec::seconds(HOST_CHECK_INTERVAL)), TimerSetTimeFlags::empty())?;
+
+        let mut reactor = Reactor
+        {
+            epoll,
+            inotify,
+            host_timer,
+            backend,
+            stop,
+            devices: HashMap::new(),
+            input: InputState::new(),
+            host_state: udc_state().unwrap_or_default(),
+            sender,
+        };
+        reactor.watch(reactor.inotify.as_raw_fd())?;
+        reactor.watch(reactor.host_timer.as_raw_fd())?;
+        reactor.watch(reactor.stop)?;
+        for entry in std::fs::read_dir(backend.directory())?
+        {
+            reactor.add_device(entry?.path())?;
+        }
+        Ok(reactor)
+    }
+
+    fn run(&mut self) -> io::Result<()>
+    {
+        let mut ready = [EpollEvent::empty(); 16];
+        loop
+        {
+            let count = match epoll_wait(self.epoll, &mut ready, -1)
+            {
+                Ok(count) => count,
+                Err(nix::errno::Errno::EINTR) => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for event in ready[..count].iter()
+            {
+                let fd = event.data() as RawFd;
+                if fd == self.stop
+                {
+                    return Ok(())
+                }
+                let result = if fd == self.inotify.as_raw_fd()
+                {
+                    self.hotplug()
+                }
+                else if fd == self.host_timer.as_raw_fd()
+                {
+                    self.check_host()
+                }
+                else
+                {
+                    self.read_device(fd, event.events())
+                };
+                // Nobody is listening anymore
+                if result.is_err()
+                {
+                    return Ok(())
+                }
+            }
+        }
+    }
+
+    fn watch(&self, fd: RawFd) -> io::Result<()>
+    {
+        let mut event = EpollEvent::new(EpollFlags::EPOLLIN, fd as u64);
+        epoll_ctl(self.epoll, EpollOp::EpollCtlAdd, fd, &mut event)?;
+        Ok(())
+    }
+
+    /// Start reading a device node if it is a keyboard that is not read yet.
+    fn add_device(&mut self, path: PathBuf) -> io::Result<()>
+    {
+        if self.devices.values().any(|device| device.path == path)
+        {
+            return Ok(())
+        }
+        let opened = match self.backend
+        {
+            InputBackend::Hidraw => open_keyboard(&path),
+            InputBackend::Evdev => open_evdev_keyboard(&path),
+        };
+        let (file, decoder) = match opened
+        {
+            Ok(Some(opened)) => opened,
+            // Not a keyboard, or not ready to be opened yet
+            Ok(None) | Err(_) => return Ok(()),
+        };
+        let fd = file.as_raw_fd();
+        self.watch(fd)?;
+        self.devices.insert(fd, Device { path, file, decoder });
+        Ok(())
+    }
+
+    fn remove_device(&mut self, fd: RawFd) -> Result<(), SendError<InputEvent>>
+    {
+        if let Some(device) = self.devices.remove(&fd)
+        {
+            let _ = epoll_ctl(self.epoll, EpollOp::EpollCtlDel, fd, None);
+            // Keys held while the keyboard was pulled out would stay down forever
+            let time = Instant::now();
+            for event in self.input.remove(&device.path)
+            {
+                self.sender.send(InputEvent::new(event, time))?;
+            }
+            self.sender.send(InputEvent::new(Events::DeviceRemoved(device.path), time))?;
+        }
+        Ok(())
+    }
+
+    fn hotplug(&mut self) -> Result<(), SendError<InputEvent>>
+    {
+        let events = match self.inotify.read_events()
+        {
+            Ok(events) => events,
+            Err(_) => return Ok(()),
+        };
+        for event in events
+        {
+            let path = match event.name
+            {
+                Some(name) => Path::new(self.backend.directory()).join(name),
+                None => continue,
+            };
+            if event.mask.contains(AddWatchFlags::IN_DELETE)
+            {
+                let removed: Vec<RawFd> = self.devices.iter()
+                    .filter(|(_, device)| device.path == path)
+                    .map(|(fd, _)| *fd)
+                    .collect();
+                for fd in removed
+                {
+                    self.remove_device(fd)?;
+                }
+            }
+            else if let Err(e) = self.add_device(path)
+            {
+                eprintln!("could not read keyboard: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_device(&mut self, fd: RawFd, flags: EpollFlags) -> Result<(), SendError<InputEvent>>
+    {
+        let mut buffer = [0u8; EVENTS_PER_READ * mem::size_of::<libc::input_event>()];
+        loop
+        {
+            let device = match self.devices.get_mut(&fd)
+            {
+                Some(device) => device,
+                None => return Ok(()),
+            };
+            let buffer = match device.decoder
+            {
+                Decoder::Evdev(_) => &mut buffer[..],
+                Decoder::Hidraw(_) => &mut buffer[..descriptor::MAXIMUM_REPORT_SIZE],
+            };
+            match device.file.read(buffer)
+            {
+                Ok(0) => break,
+                Ok(length) =>
+                {
+                    let time = Instant::now();
+                    let reports = match &mut device.decoder
+                    {
+                        Decoder::Evdev(keyboard) => evdev_reports(keyboard, &buffer[..length]),
+                        // Reports of the other collections, like media keys, are skipped
+                        Decoder::Hidraw(formats) => formats.iter()
+                            .find_map(|format| format.boot_report(&buffer[..length]))
+                            .map(|report| report.to_vec())
+                            .into_iter()
+                            .collect(),
+                    };
+                    for report in reports
+                    {
+                        for event in self.input.update(&device.path, &report)
+                        {
+                            self.sender.send(InputEvent::new(event, time))?;
+                        }
+                    }
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock =>
+                {
+                    if flags.intersects(EpollFlags::EPOLLHUP | EpollFlags::EPOLLERR)
+                    {
+                        break;
+                    }
+                    return Ok(())
+                },
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+        // End of file or an error, the keyboard is gone
+        self.remove_device(fd)
+    }
+
+    fn check_host(&mut self) -> Result<(), SendError<InputEvent>>
+    {
+        // Clear the expiration count, the timer keeps waking epoll until it is read
+        let mut expirations = [0u8; 8];
+        let _ = nix::unistd::read(self.host_timer.as_raw_fd(), &mut expirations);
+
+        let state = udc_state().unwrap_or_default();
+        if state == self.host_state
+        {
+            return Ok(())
+        }
+        let event = match state.as_str()
+        {
+            "suspended" => Some(Events::HostSuspended),
+            "not attached" | "" => Some(Events::HostDisconnected),
+            _ => None,
+        };
+        self.host_state = state;
+        match event
+        {
+            Some(event) => self.sender.send(InputEvent::now(event)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Reactor
+{
+    fn drop(&mut self)
+    {
+        let _ = nix::unistd::close(self.epoll);
+    }
+}
+
+/// Open a hidraw node if its report descriptor has a keyboard.
+fn open_keyboard(path: &Path) -> io::Result<Option<(File, Decoder)>>
+{
+    if !path.to_string_lossy().contains("hidraw")
+    {
+        return Ok(None)
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)?;
+    let fd = file.as_raw_fd();
+
+    let mut size = 0;
+    unsafe { hid_read_sz(fd, &mut size)?; }
+
+    let mut desc_raw = hidraw_report_descriptor { size: size as u32, value: [0u8; HID_MAX_DESCRIPTOR_SIZE] };
+    unsafe { hid_read_descr(fd, &mut desc_raw)?; }
+    let data = &desc_raw.value[..(desc_raw.size as usize).min(HID_MAX_DESCRIPTOR_SIZE)];
+
+    let formats = descriptor::parse(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)))?;
+    if formats.is_empty()
+    {
+        return Ok(None)
+    }
+    Ok(Some((file, Decoder::Hidraw(formats))))
+}
+
+/// Open an evdev node if it can send letters and enter, and grab it so nothing else reads its keys.
+fn open_evdev_keyboard(path: &Path) -> io::Result<Option<(File, Decoder)>>
+{
+    if !path.to_string_lossy().contains("event")
+    {
+        return Ok(None)
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)?;
+    let fd = file.as_raw_fd();
+
+    let mut key_bits = [0u8; 96];
+    unsafe { evdev_key_bits(fd, &mut key_bits)?; }
+    let has_key = |code: usize| key_bits[code / 8] & (1 << (code % 8)) != 0;
+    if !has_key(KEY_A) || !has_key(KEY_ENTER)
+    {
+        return Ok(None)
+    }
+    // Released again when the file is closed
+    unsafe { evdev_grab(fd, 1)?; }
+    Ok(Some((file, Decoder::Evdev(EvdevKeyboard::new()))))
+}
+
+/// Turn the events read from an evdev keyboard into the boot reports of every completed batch.
+fn evdev_reports(keyboard: &mut EvdevKeyboard, buffer: &[u8]) -> Vec<Vec<u8>>
+{
+    buffer.chunks_exact(mem::size_of::<libc::input_event>())
+        .filter_map(|chunk|
+        {
+            // The kernel only hands out whole events, the buffer has no alignment so the event is copied out
+            let event: libc::input_event = unsafe { std::ptr::read_unaligned(chunk.as_ptr() as *const libc::input_event) };
+            keyboard.event(event.type_, event.code, event.value)
+        })
+        .map(|report| report.to_vec())
+        .collect()
+}
+
+/// Read the state of the first usb device controller, empty if there is none.
+fn udc_state() -> io::Result<String>
+{
+    match std::fs::read_dir("/sys/class/udc/")?.next()
+    {
+        Some(entry) => Ok(std::fs::read_to_string(entry?.path().join("state"))?.trim().to_owned()),
+        None => Ok(String::new()),
+    }
+}
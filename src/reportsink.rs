@@ -0,0 +1,227 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+use fobword_core::pace::TypingPace;
+use fobword_core::report::{ReportProtocol, MAX_REPORT_LENGTH};
+
+/// Room for a report with no keys or modifiers pressed, of any of the protocols.
+const RELEASED: [u8; MAX_REPORT_LENGTH] = [0; MAX_REPORT_LENGTH];
+/// How often a write is tried again when the gadget is busy.
+const RETRY_ATTEMPTS: u32 = 50;
+const RETRY_DELAY: Duration = Duration::from_millis(2);
+
+/// The gadget the panic hook and signal handlers release the keys on, -1 when there is none.
+static RELEASE_FD: AtomicI32 = AtomicI32::new(-1);
+/// The length of the reports of that gadget.
+static RELEASE_LENGTH: AtomicUsize = AtomicUsize::new(8);
+static INSTALL_HANDLERS: Once = Once::new();
+
+/// Writes HID reports to the gadget and makes sure no key stays pressed on the host.
+///
+/// An all-released report is sent when a write fails, when the sink is dropped,
+/// when the program panics and when it is stopped with SIGTERM, SIGINT or SIGHUP.
+pub struct ReportSink
+{
+    file: File,
+    protocol: ReportProtocol,
+}
+
+impl ReportSink
+{
+    /// Open the gadget, set up for reports of `protocol`, and install the panic hook and signal handlers.
+    ///
+    /// The gadget is opened non-blocking, a host that stops reading makes writes fail instead of hang.
+    pub fn open(path: &Path, protocol: ReportProtocol) -> io::Result<ReportSink>
+    {
+        let file = OpenOptions::new().write(true).custom_flags(libc::O_NONBLOCK).open(path)?;
+        RELEASE_LENGTH.store(protocol.length(), Ordering::SeqCst);
+        RELEASE_FD.store(file.as_raw_fd(), Ordering::SeqCst);
+        INSTALL_HANDLERS.call_once(ReportSink::install_handlers);
+        Ok(ReportSink { file, protocol })
+    }
+
+    /// Write a single report, trying again for a moment while the gadget is busy.
+    pub fn write_report(&mut self, report: &[u8]) -> io::Result<usize>
+    {
+        let mut attempts = 0;
+        loop
+        {
+            match self.file.write(report)
+            {
+                Ok(written) if written == report.len() => return Ok(written),
+                Ok(_) => return Err(io::Error::new(io::ErrorKind::WriteZero, "the gadget took part of a report")),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && attempts < RETRY_ATTEMPTS =>
+                {
+                    attempts += 1;
+                    thread::sleep(RETRY_DELAY);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Write a series of reports, releasing every key if one of them fails.
+    pub fn write_reports<I, R>(&mut self, reports: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = R>,
+        R: AsRef<[u8]>,
+    {
+        self.write_paced(reports, TypingPace::default())
+    }
+
+    /// Write a series of reports at a pace slow hosts can keep up with, releasing every key if one of them fails.
+    pub fn write_paced<I, R>(&mut self, reports: I, pace: TypingPace) -> io::Result<()>
+    where
+        I: IntoIterator<Item = R>,
+        R: AsRef<[u8]>,
+    {
+        for report in reports
+        {
+            let report = report.as_ref();
+            if let Err(e) = self.write_report(report)
+            {
+                self.release_all();
+                return Err(e);
+            }
+            let delay = pace.delay_after(report.iter().any(|byte| *byte != 0));
+            if delay > Duration::from_millis(0)
+            {
+                thread::sleep(delay);
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a report with nothing pressed, errors are ignored since there is nothing left to do about them.
+    pub fn release_all(&mut self)
+    {
+        let _ = self.write_report(&RELEASED[..self.protocol.length()]);
+    }
+
+    fn install_handlers()
+    {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info|
+        {
+            release_raw();
+            previous_hook(info);
+        }));
+
+        for signal in [libc::SIGTERM, libc::SIGINT, libc::SIGHUP].iter()
+        {
+            unsafe { libc::signal(*signal, release_on_signal as *const () as libc::sighandler_t); }
+        }
+    }
+}
+
+impl Drop for ReportSink
+{
+    fn drop(&mut self)
+    {
+        self.release_all();
+        let _ = RELEASE_FD.compare_exchange(self.file.as_raw_fd(), -1, Ordering::SeqCst, Ordering::SeqCst);
+    }
+}
+
+/// Release the keys with a plain write, this is safe to call from a signal handler.
+fn release_raw()
+{
+    let fd = RELEASE_FD.load(Ordering::SeqCst);
+    if fd >= 0
+    {
+        let length = RELEASE_LENGTH.load(Ordering::SeqCst).min(RELEASED.len());
+        unsafe { libc::write(fd, RELEASED.as_ptr() as *const libc::c_void, length); }
+    }
+}
+
+extern "C" fn release_on_signal(signal: libc::c_int)
+{
+    release_raw();
+    // Stop the way the signal would have without the handler
+    unsafe
+    {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+#[cfg(test)]
+mod reportsinktests
+{
+    use super::*;
+    use std::io::Read;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf
+    {
+        let path = std::env::temp_dir().join(format!("fobword-reportsink-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_write_paced_writes_every_report()
+    {
+        let path = temp_path("paced");
+        std::fs::write(&path, b"").unwrap();
+        let reports = [[2u8, 0, 4, 0, 0, 0, 0, 0], [0; 8]];
+        let pace = TypingPace { report_delay_ms: 1, char_delay_ms: 1, hold_ms: 1 };
+
+        let mut sink = ReportSink::open(&path, ReportProtocol::Boot).unwrap();
+        sink.write_paced(reports.iter(), pace).unwrap();
+        drop(sink);
+
+        // Dropping the sink sends one more released report
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!([2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], written[..]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_release_all_writes_a_released_report_of_the_protocol()
+    {
+        let path = temp_path("release");
+        std::fs::write(&path, b"").unwrap();
+
+        let mut sink = ReportSink::open(&path, ReportProtocol::Nkro).unwrap();
+        sink.release_all();
+        assert_eq!(vec![0; ReportProtocol::Nkro.length()], std::fs::read(&path).unwrap());
+        drop(sink);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_full_gadget_fails_instead_of_blocking()
+    {
+        let path = temp_path("fifo");
+        nix::unistd::mkfifo(&path, nix::sys::stat::Mode::S_IRWXU).unwrap();
+        // A fifo can only be opened for writing without blocking once it has a reader
+        let mut reader = OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(&path).unwrap();
+        let mut sink = ReportSink::open(&path, ReportProtocol::Boot).unwrap();
+
+        let report = [2u8, 0, 4, 0, 0, 0, 0, 0];
+        let error = loop
+        {
+            if let Err(e) = sink.write_paced([report].iter(), TypingPace::default())
+            {
+                break e
+            }
+        };
+        assert_eq!(io::ErrorKind::WouldBlock, error.kind());
+
+        // Once the host reads again the gadget takes reports again
+        let mut buffer = [0; 4096];
+        while reader.read(&mut buffer).is_ok_and(|read| read > 0) {}
+        sink.write_report(&report).unwrap();
+        drop(sink);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
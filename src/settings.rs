@@ -0,0 +1,125 @@
+use fobword_core::error::DataHandleError;
+use fobword_core::lineeditor::Mask;
+use fobword_core::pace::HostProfiles;
+use fobword_core::report::ReportProtocol;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::reactor::InputBackend;
+
+use super::*;
+
+/// The settings of the program, stored in their own file next to the vault.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppSettings {
+    pub output: String,
+    pub macro_key: Vec<u8>,
+    pub output_location: String,
+    pub input_location: String,
+    /// Erase the vault after this many failed unlock attempts, never if not set
+    #[serde(default)]
+    pub wipe_after_failed_attempts: Option<u32>,
+    /// Lock after this many seconds without input, never if not set
+    #[serde(default)]
+    pub lock_timeout: Option<u64>,
+    /// Device and host events that lock the device
+    #[serde(default)]
+    pub lock_on: LockPolicy,
+    /// How many back-ups of the vault file to keep
+    #[serde(default = "default_backup_generations")]
+    pub backup_generations: usize,
+    /// Encrypt and write the vault after every change, this keeps the master password in memory while unlocked
+    #[serde(default)]
+    pub autosave: bool,
+    /// How passwords are shown while they are typed
+    #[serde(default)]
+    pub secret_mask: Mask,
+    /// Read keyboards from hidraw or evdev nodes
+    #[serde(default)]
+    pub input_backend: InputBackend,
+    /// The reports written to the gadget, it has to be set up with the descriptor of the same protocol
+    #[serde(default)]
+    pub report_protocol: ReportProtocol,
+    /// How fast passwords are typed on each kind of host
+    #[serde(default)]
+    pub host_profiles: HostProfiles,
+}
+
+fn default_backup_generations() -> usize {
+    3
+}
+
+impl AppSettings {
+    /// The settings used when there is no settings file.
+    pub fn new(paths: &Paths) -> AppSettings {
+        let layout = paths.default_layout().to_string_lossy().into_owned();
+        AppSettings {
+            output: "/dev/hidg0".to_owned(),
+            macro_key: vec![0x02, 0, 0x3au8, 0, 0, 0, 0, 0,],
+            input_location: layout.clone(),
+            output_location: layout,
+            wipe_after_failed_attempts: None,
+            lock_timeout: Some(300),
+            lock_on: LockPolicy::default(),
+            backup_generations: default_backup_generations(),
+            autosave: false,
+            secret_mask: Mask::default(),
+            input_backend: InputBackend::default(),
+            report_protocol: ReportProtocol::default(),
+            host_profiles: HostProfiles::default(),
+        }
+    }
+
+    /// Serialize the settings as a string of YAML.
+    pub fn to_yaml(&self) -> Result<String, DataHandleError> {
+        Ok(serde_yaml::to_string(&self)?)
+    }
+
+    /// Deserialize the settings from a string of YAML and validate them.
+    pub fn from_yaml(buffer: &str) -> Result<AppSettings, DataHandleError> {
+        let settings: AppSettings = serde_yaml::from_str(buffer)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Check the values that deserialize fine but can't be used.
+    pub fn validate(&self) -> Result<(), DataHandleError> {
+        // The macro key is a HID report, byte 0 is the modifier and byte 2 the first key code
+        if self.macro_key.len() < 3 {
+            return invalid(format!("macro_key needs at least 3 bytes (modifier, reserved, key code), found {}", self.macro_key.len()));
+        }
+        if self.macro_key[2] == 0 {
+            return invalid("macro_key has no key code in byte 3".to_owned());
+        }
+        if self.output.is_empty() {
+            return invalid("output can not be empty".to_owned());
+        }
+        if self.input_location.is_empty() || self.output_location.is_empty() {
+            return invalid("input_location and output_location can not be empty".to_owned());
+        }
+        if self.wipe_after_failed_attempts == Some(0) {
+            return invalid("wipe_after_failed_attempts must be at least 1, leave it out to never wipe".to_owned());
+        }
+        if self.lock_timeout == Some(0) {
+            return invalid("lock_timeout must be at least 1 second, leave it out to never lock".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Check that the layout files exist, unless a layout was given on the command line.
+    pub fn validate_layouts(&self, paths: &Paths) -> Result<(), DataHandleError> {
+        if paths.layout.is_some() {
+            return Ok(());
+        }
+        for (name, location) in [("input_location", &self.input_location), ("output_location", &self.output_location)].iter() {
+            if !Path::new(location).exists() {
+                return invalid(format!("{} '{}' does not exist", name, location));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: String) -> Result<(), DataHandleError> {
+    Err(DataHandleError::InvalidSettings(message))
+}
@@ -0,0 +1,217 @@
+use fobword_core::config::{Config, LockedData};
+use fobword_core::converter::{Converter, Modifier};
+use fobword_core::error::DataHandleError;
+use fobword_core::lockout::Lockout;
+use std::path::PathBuf;
+
+use super::*;
+
+/// Master passwords shorter than this are refused.
+const MINIMUM_PASSWORD_LENGTH: usize = 8;
+
+/// Set up a device that has no vault yet.
+///
+/// Picks the keyboard layout and macro key, then either imports `import.yaml` or creates an empty vault with a new master password.
+/// The settings and vault are only written at the end, a device that loses power halfway starts the wizard again.
+/// Pressing Escape at any question starts it over as well.
+pub fn run(iohelper: &mut IOhelper, settings: &mut AppSettings, lockout: &mut Lockout, paths: &Paths) -> Result<LockedData, DataHandleError>
+{
+    iohelper.screen_on()?;
+    loop
+    {
+        let mut new_settings = settings.clone();
+        match setup(iohelper, &mut new_settings, lockout, paths)
+        {
+            Err(DataHandleError::Cancelled) => iohelper.converter = App::converter(settings, paths)?,
+            result =>
+            {
+                *settings = new_settings;
+                return result
+            },
+        }
+    }
+}
+
+fn setup(iohelper: &mut IOhelper, settings: &mut AppSettings, lockout: &mut Lockout, paths: &Paths) -> Result<LockedData, DataHandleError>
+{
+    iohelper.clear_screen();
+    iohelper.println("First start, setting up")?;
+
+    if paths.layout.is_none()
+    {
+        choose_layout(iohelper, settings, paths)?;
+    }
+    choose_macro_key(iohelper, settings)?;
+
+    let data = match import_backup(iohelper, settings, lockout, paths)?
+    {
+        Some(data) => data,
+        None =>
+        {
+            let password = choose_password(iohelper)?;
+            LockedData::new(&password)?
+        }
+    };
+
+    fileutilities::atomic_write(&paths.config, settings.to_yaml()?.as_bytes())?;
+    fileutilities::atomic_write(&paths.vault, data.to_yaml()?.as_bytes())?;
+    iohelper.println("Setup done")?;
+    Ok(data)
+}
+
+/// Let the user pick one of the layouts next to the settings file.
+fn choose_layout(iohelper: &mut IOhelper, settings: &mut AppSettings, paths: &Paths) -> Result<(), DataHandleError>
+{
+    let mut layouts: Vec<PathBuf> = std::fs::read_dir(paths.config_directory())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.to_string_lossy().ends_with("-layout.txt"))
+        .collect();
+    layouts.sort();
+    if layouts.is_empty()
+    {
+        return Ok(())
+    }
+
+    iohelper.println("Pick a layout:")?;
+    for (index, layout) in layouts.iter().enumerate()
+    {
+        let name = layout.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        iohelper.println(&format!("{} {}", index + 1, name))?;
+    }
+    let layout = loop
+    {
+        match pick(iohelper, layouts.len())?
+        {
+            Some(index) => break &layouts[index],
+            None => iohelper.println(&format!("Type 1 to {}", layouts.len()))?,
+        };
+    };
+
+    let location = layout.to_string_lossy().into_owned();
+    let mut converter = Converter::from_paths(layout, layout)?;
+    converter.add_macro(Modifier::from(settings.macro_key[0]), settings.macro_key[2]);
+    iohelper.converter = converter;
+    settings.input_location = location.clone();
+    settings.output_location = location;
+    Ok(())
+}
+
+/// Let the user press the key combination that opens the device.
+fn choose_macro_key(iohelper: &mut IOhelper, settings: &mut AppSettings) -> Result<(), DataHandleError>
+{
+    loop
+    {
+        iohelper.println("Press the macro key:")?;
+        let (modifier, key_code) = iohelper.next_raw_key()?;
+        iohelper.println(&format!("{:?} + key {}, keep? (y/n)", Modifier::from(modifier), key_code))?;
+        if iohelper.read_line()? == "y"
+        {
+            settings.macro_key = vec![modifier, 0, key_code, 0, 0, 0, 0, 0];
+            iohelper.converter.add_macro(Modifier::from(modifier), key_code);
+            return Ok(())
+        }
+    }
+}
+
+/// Choose the master password, it has to be typed twice.
+fn choose_password(iohelper: &mut IOhelper) -> Result<String, DataHandleError>
+{
+    loop
+    {
+        iohelper.println("Choose master password:")?;
+        let password = iohelper.read_secret()?;
+        if password.chars().count() < MINIMUM_PASSWORD_LENGTH
+        {
+            iohelper.println(&format!("Use at least {} characters", MINIMUM_PASSWORD_LENGTH))?;
+            continue;
+        }
+        iohelper.println("Confirm password:")?;
+        if iohelper.read_secret()? == password
+        {
+            return Ok(password)
+        }
+        iohelper.println("The passwords do not match")?;
+    }
+}
+
+/// Offer to import `import.yaml` from next to the vault, it is only used after its password opened it.
+///
+/// Back-ups of the vault itself are loaded on start already, the setup only runs when none of them could be read.
+/// Failed passwords count towards the same lockout as unlocking, reaching the wipe threshold erases the import.
+fn import_backup(iohelper: &mut IOhelper, settings: &AppSettings, lockout: &mut Lockout, paths: &Paths) -> Result<Option<LockedData>, DataHandleError>
+{
+    let import = paths.import();
+    if !import.exists()
+    {
+        return Ok(None)
+    }
+
+    iohelper.println("Import import.yaml? (y/n)")?;
+    if iohelper.read_line()? != "y"
+    {
+        return Ok(None)
+    }
+
+    let buffer = std::fs::read_to_string(&import)?;
+    // The import can be a config file from before the vault had its own file
+    let mut data = match LockedData::from_yaml(&buffer)
+    {
+        Ok(data) => data,
+        Err(_) => match Config::<AppSettings>::from_yaml(&buffer)
+        {
+            Ok(Config { data: Some(data), .. }) => data,
+            _ =>
+            {
+                iohelper.println("Not a vault back-up")?;
+                return Ok(None)
+            }
+        },
+    };
+
+    iohelper.println("Back-up password:")?;
+    loop
+    {
+        App::wait_for(iohelper, lockout)?;
+        let password = iohelper.read_secret()?;
+        if password.is_empty()
+        {
+            return Ok(None)
+        }
+
+        // Count the attempt before checking it, so cutting the power during the check does not skip it
+        lockout.record_failure();
+        App::write_lockout(paths, lockout)?;
+        match data.unlock(&password)
+        {
+            Ok(_) =>
+            {
+                lockout.reset();
+                App::write_lockout(paths, lockout)?;
+                return Ok(Some(data))
+            },
+            Err(DataHandleError::ArgonError(password_hash::Error::Password)) =>
+            {
+                if lockout.should_wipe(settings.wipe_after_failed_attempts)
+                {
+                    fileutilities::secure_erase(&import)?;
+                    lockout.reset();
+                    App::write_lockout(paths, lockout)?;
+                    iohelper.println("Too many attempts, import erased")?;
+                    return Ok(None)
+                }
+                iohelper.println(&format!("Invalid password, {} failed attempts, leave empty to skip", lockout.failed_attempts))?;
+            },
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Read a number from 1 to `count` and return it as an index, `None` if it was something else.
+fn pick(iohelper: &mut IOhelper, count: usize) -> Result<Option<usize>, DataHandleError>
+{
+    Ok(match iohelper.read_line()?.trim().parse::<usize>()
+    {
+        Ok(number) if number >= 1 && number <= count => Some(number - 1),
+        _ => None,
+    })
+}